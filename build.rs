@@ -4,6 +4,19 @@ use std::io::BufReader;
 use std::path::PathBuf;
 use xz2::bufread::{XzDecoder, XzEncoder};
 
+const LANGUAGES: &[&str] = &["en", "de", "es"];
+
+fn languages() -> Vec<&'static str> {
+  // english is always embedded, while other languages are opt-in through cargo features
+  LANGUAGES
+    .iter()
+    .copied()
+    .filter(|lang| {
+      *lang == "en" || env::var(format!("CARGO_FEATURE_{}", lang.to_uppercase())).is_ok()
+    })
+    .collect()
+}
+
 fn build_treesitter() {
   println!("cargo:rerun-if-changed=vendor/tree-sitter-markdown/parser.c");
   println!("cargo:rerun-if-changed=vendor/tree-sitter-markdown/scanner.cc");
@@ -27,19 +40,33 @@ fn build_nlprule_binary() -> std::result::Result<(), Box<(dyn std::error::Error
   let out = env::var("OUT_DIR").expect("OUT_DIR exists in env vars. qed");
   let out = PathBuf::from(out);
 
-  println!("cargo:rerun-if-changed=vendor/nlprule-data/en_rules.bin.xz");
-  println!("cargo:rerun-if-changed=vendor/nlprule-data/en_tokenizer.bin.xz");
-  println!("cargo:rerun-if-changed={}/en_rules.bin.gz", out.display());
-  println!(
-    "cargo:rerun-if-changed={}/en_tokenizer.bin.gz",
-    out.display()
-  );
+  let languages = languages();
+  for lang in &languages {
+    println!(
+      "cargo:rerun-if-changed=vendor/nlprule-data/{}_rules.bin.xz",
+      lang
+    );
+    println!(
+      "cargo:rerun-if-changed=vendor/nlprule-data/{}_tokenizer.bin.xz",
+      lang
+    );
+    println!(
+      "cargo:rerun-if-changed={}/{}_rules.bin.gz",
+      out.display(),
+      lang
+    );
+    println!(
+      "cargo:rerun-if-changed={}/{}_tokenizer.bin.gz",
+      out.display(),
+      lang
+    );
+  }
 
   let cwd = env::current_dir().expect("Current dir must exist. qed");
 
   let cache_dir = Some(cwd.join("vendor/nlprule-data"));
 
-  nlprule_build::BinaryBuilder::new(&["en"], &out)
+  nlprule_build::BinaryBuilder::new(&languages, &out)
     .fallback_to_build_dir(false)
     .cache_dir(cache_dir)
     .transform(
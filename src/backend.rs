@@ -1,4 +1,14 @@
-use crate::{document::Document, validator::Validator};
+use crate::{
+  completion::{self, CompletionKind},
+  config::{Config, Settings, CONFIG_FILENAME},
+  dictionary::{self, ADD_TO_DICTIONARY_COMMAND, DICTIONARY_PATH},
+  document::Document,
+  headings::Heading,
+  link_validator::LinkValidator,
+  stats::{DocumentStats, DOCUMENT_STATS_METHOD},
+  validator::{Binaries, Validators},
+  workspace::{self, is_image_file, is_markdown_file, relative_path, Workspace},
+};
 
 use async_std::{
   channel::{unbounded, Receiver, Sender},
@@ -6,8 +16,19 @@ use async_std::{
   sync::{Arc, RwLock},
 };
 use im::hashmap::HashMap;
-use lspower::{jsonrpc::Result, lsp::*, Client, LanguageServer};
-use std::time::Duration;
+use lspower::{
+  jsonrpc::{Error, Result},
+  lsp::*,
+  Client, LanguageServer,
+};
+use serde::Deserialize;
+use std::{
+  collections::HashSet,
+  fs,
+  path::{Path, PathBuf},
+  sync::atomic::{AtomicBool, Ordering},
+  time::Duration,
+};
 use url::Url;
 
 enum BackendEvent {
@@ -16,17 +37,35 @@ enum BackendEvent {
 
 pub struct Backend {
   client: Arc<Client>,
-  validator: Arc<Validator>,
+  validators: Arc<Validators>,
+  binaries: Arc<Binaries>,
   files: Arc<RwLock<HashMap<Url, Document>>>,
+  diagnostics: Arc<RwLock<HashMap<Url, Vec<Diagnostic>>>>,
+  stats: Arc<RwLock<HashMap<Url, (i32, DocumentStats)>>>,
+  configs: Arc<RwLock<HashMap<PathBuf, Arc<Config>>>>,
+  settings: Arc<RwLock<Settings>>,
+  workspace: Arc<RwLock<Workspace>>,
+  root: Arc<RwLock<Option<PathBuf>>>,
+  supports_configuration: AtomicBool,
+  supports_watched_files: AtomicBool,
   events: (Sender<BackendEvent>, Receiver<BackendEvent>),
 }
 
 impl Backend {
-  pub fn new(client: Client) -> Self {
+  pub fn new(client: Client, binaries: Binaries) -> Self {
     Self {
       client: Arc::new(client),
-      validator: Arc::new(Validator::new()),
+      validators: Arc::new(Validators::default()),
+      binaries: Arc::new(binaries),
       files: Arc::new(RwLock::new(HashMap::new())),
+      diagnostics: Arc::new(RwLock::new(HashMap::new())),
+      stats: Arc::new(RwLock::new(HashMap::new())),
+      configs: Arc::new(RwLock::new(HashMap::new())),
+      settings: Arc::new(RwLock::new(Settings::default())),
+      workspace: Arc::new(RwLock::new(Workspace::default())),
+      root: Arc::new(RwLock::new(None)),
+      supports_configuration: AtomicBool::new(false),
+      supports_watched_files: AtomicBool::new(false),
       events: unbounded(),
     }
   }
@@ -36,27 +75,214 @@ impl Backend {
     let _ = sender.send(event).await;
   }
 
+  async fn send_all_diagnostics(&self) {
+    let uris = self
+      .files
+      .read()
+      .await
+      .keys()
+      .cloned()
+      .collect::<Vec<Url>>();
+    for uri in uris {
+      self.send_event(BackendEvent::SendDiagnostics(uri)).await;
+    }
+  }
+
+  async fn update_settings(&self, settings: Settings) {
+    log::debug!("update_settings: {:?}", settings);
+    *self.settings.write().await = settings;
+    // configs and statistics depend on the settings, e.g. on the language
+    *self.configs.write().await = HashMap::new();
+    *self.stats.write().await = HashMap::new();
+    self.send_all_diagnostics().await;
+  }
+
+  async fn fetch_settings(&self) -> Option<Settings> {
+    if !self.supports_configuration.load(Ordering::Relaxed) {
+      return None;
+    }
+
+    let items = vec![ConfigurationItem {
+      scope_uri: None,
+      section: Some("prosemd".to_string()),
+    }];
+    match self.client.configuration(items).await {
+      Ok(mut values) if !values.is_empty() => Settings::from_value(values.remove(0)),
+      Ok(_) => None,
+      Err(err) => {
+        log::warn!("failed to fetch configuration: {}", err);
+        None
+      }
+    }
+  }
+
+  async fn dictionary_path(&self, uri: Option<&Url>) -> Option<PathBuf> {
+    // words are added to the closest workspace dictionary, falling back to the user's dictionary
+    let document_dir = uri
+      .and_then(|uri| uri.to_file_path().ok())
+      .and_then(|path| path.parent().map(|dir| dir.to_path_buf()));
+    if let Some(path) = document_dir.as_deref().and_then(|dir| {
+      dictionary::find_workspace_dictionaries(dir)
+        .into_iter()
+        .next()
+    }) {
+      return Some(path);
+    }
+
+    match self.root.read().await.as_ref() {
+      Some(root) => Some(root.join(DICTIONARY_PATH)),
+      None => dictionary::user_dictionary_path(),
+    }
+  }
+
+  async fn document_config(&self, path: Option<&Path>) -> Arc<Config> {
+    let settings = self.settings.read().await.clone();
+    cached_config(&self.configs, &settings, path, &self.binaries).await
+  }
+
+  async fn format(&self, uri: &Url, range: Option<Range>) -> Option<Vec<TextEdit>> {
+    let path = uri.to_file_path().ok();
+    let config = self.document_config(path.as_deref()).await;
+    let files = self.files.read().await;
+    Some(files.get(uri)?.format(&config, range))
+  }
+
+  async fn stats(&self, uri: &Url) -> Option<DocumentStats> {
+    let document = self.files.read().await.get(uri).cloned()?;
+    // tokenizing every paragraph is slow, so statistics are only computed once per version
+    if let Some((version, stats)) = self.stats.read().await.get(uri) {
+      if *version == document.version() {
+        return Some(stats.clone());
+      }
+    }
+
+    let path = uri.to_file_path().ok();
+    let config = self.document_config(path.as_deref()).await;
+    let language = document.language(path.as_deref(), &config);
+    let stats = document.stats(&self.validators, language, &config);
+    self
+      .stats
+      .write()
+      .await
+      .insert(uri.clone(), (document.version(), stats.clone()));
+    Some(stats)
+  }
+
+  async fn target_headings(&self, uri: &Url) -> Option<Vec<Heading>> {
+    // open documents take precedence over what's been indexed from disk
+    if let Some(document) = self.files.read().await.get(uri) {
+      return Some(document.headings());
+    }
+    let path = uri.to_file_path().ok()?;
+    self.workspace.write().await.headings(&path).cloned()
+  }
+
+  async fn index_workspace(&self) {
+    if let Some(root) = self.root.read().await.clone() {
+      let workspace = Arc::clone(&self.workspace);
+      async_std::task::spawn(async move {
+        let index = async_std::task::spawn_blocking(move || Workspace::from_dir(&root)).await;
+        workspace.write().await.merge(index);
+      });
+    }
+  }
+
+  async fn watch_files(&self) {
+    if !self.supports_watched_files.load(Ordering::Relaxed) {
+      return;
+    }
+
+    let options = DidChangeWatchedFilesRegistrationOptions {
+      watchers: vec![
+        FileSystemWatcher {
+          glob_pattern: "**/*.{md,markdown,png,jpg,jpeg,gif,svg,webp,avif,bmp,ico}".to_string(),
+          kind: None,
+        },
+        // configs are cached, so their files and dictionaries are watched for changes as well
+        FileSystemWatcher {
+          glob_pattern: format!("**/{}", CONFIG_FILENAME),
+          kind: None,
+        },
+        FileSystemWatcher {
+          glob_pattern: format!("**/{}", DICTIONARY_PATH),
+          kind: None,
+        },
+      ],
+    };
+    let registration = Registration {
+      id: "prosemd-watched-files".to_string(),
+      method: "workspace/didChangeWatchedFiles".to_string(),
+      register_options: serde_json::to_value(options).ok(),
+    };
+    if let Err(err) = self.client.register_capability(vec![registration]).await {
+      log::warn!("failed to watch files: {}", err);
+    }
+  }
+
   fn events_loop(&self) {
     let mut events = {
       let (_, receiver) = &self.events;
-      receiver.clone().throttle(Duration::from_millis(100))
+      receiver.clone()
     };
 
     let client = Arc::clone(&self.client);
-    let validator = Arc::clone(&self.validator);
+    let validators = Arc::clone(&self.validators);
+    let binaries = Arc::clone(&self.binaries);
     let files = Arc::clone(&self.files);
+    let published = Arc::clone(&self.diagnostics);
+    let configs = Arc::clone(&self.configs);
+    let settings = Arc::clone(&self.settings);
+    let workspace = Arc::clone(&self.workspace);
+    let root = Arc::clone(&self.root);
 
     async_std::task::spawn(async move {
       while let Some(event) = events.next().await {
-        match event {
-          BackendEvent::SendDiagnostics(uri) => {
-            if let Some(document) = files.read().await.get(&uri) {
-              let diagnostics = document.diagnostics(&validator);
-              let version = document.version();
-              client
-                .publish_diagnostics(uri, diagnostics, Some(version))
-                .await;
+        // events are debounced and only sent once per document after changes settle down
+        let debounce = settings.read().await.debounce;
+        async_std::task::sleep(Duration::from_millis(debounce)).await;
+
+        let mut uris = HashSet::new();
+        let mut next_event = Some(event);
+        while let Some(event) = next_event {
+          match event {
+            BackendEvent::SendDiagnostics(uri) => uris.insert(uri),
+          };
+          next_event = events.try_recv().ok();
+        }
+
+        let settings = settings.read().await.clone();
+        let root = root.read().await.clone();
+        for uri in uris {
+          // documents are cloned, so that changes can come in while they are checked
+          let document = files.read().await.get(&uri).cloned();
+          if let Some(document) = document {
+            let path = uri.to_file_path().ok();
+            // open documents are indexed as they change, so links to them see their latest headings
+            if let Some(path) = &path {
+              workspace.write().await.insert(path, document.headings());
             }
+
+            let diagnostics = if settings.validate {
+              let config = cached_config(&configs, &settings, path.as_deref(), &binaries).await;
+              let language = document.language(path.as_deref(), &config);
+              let mut diagnostics = document.diagnostics(&validators, None, language, &config);
+              // the workspace is only locked for links, since other requests need it while rules run
+              let mut workspace = workspace.write().await;
+              let mut link_validator = LinkValidator::new(&mut workspace, &uri, root.as_deref());
+              diagnostics.extend(document.link_diagnostics(&mut link_validator, &config));
+              diagnostics
+            } else {
+              Vec::new()
+            };
+            let version = document.version();
+            // diagnostics are kept around to explain them on hover
+            published
+              .write()
+              .await
+              .insert(uri.clone(), diagnostics.clone());
+            client
+              .publish_diagnostics(uri, diagnostics, Some(version))
+              .await;
           }
         }
       }
@@ -66,8 +292,28 @@ impl Backend {
 
 #[lspower::async_trait]
 impl LanguageServer for Backend {
-  async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+  async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
     log::debug!("initialize");
+
+    let workspace_capabilities = params.capabilities.workspace.as_ref();
+    let supports_configuration = workspace_capabilities
+      .and_then(|workspace| workspace.configuration)
+      .unwrap_or(false);
+    self
+      .supports_configuration
+      .store(supports_configuration, Ordering::Relaxed);
+    let supports_watched_files = workspace_capabilities
+      .and_then(|workspace| workspace.did_change_watched_files.as_ref())
+      .and_then(|watched_files| watched_files.dynamic_registration)
+      .unwrap_or(false);
+    self
+      .supports_watched_files
+      .store(supports_watched_files, Ordering::Relaxed);
+    if let Some(settings) = params.initialization_options.and_then(Settings::from_value) {
+      *self.settings.write().await = settings;
+    }
+    *self.root.write().await = params.root_uri.and_then(|uri| uri.to_file_path().ok());
+
     self.events_loop();
 
     Ok(InitializeResult {
@@ -76,12 +322,94 @@ impl LanguageServer for Backend {
         text_document_sync: Some(TextDocumentSyncCapability::Kind(
           TextDocumentSyncKind::Incremental,
         )),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
         code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions {
+          trigger_characters: Some(vec![
+            "(".to_string(),
+            "[".to_string(),
+            "#".to_string(),
+            "/".to_string(),
+          ]),
+          ..CompletionOptions::default()
+        }),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+        document_link_provider: Some(DocumentLinkOptions {
+          resolve_provider: Some(false),
+          work_done_progress_options: WorkDoneProgressOptions::default(),
+        }),
+        code_lens_provider: Some(CodeLensOptions {
+          resolve_provider: Some(false),
+        }),
+        document_formatting_provider: Some(OneOf::Left(true)),
+        document_range_formatting_provider: Some(OneOf::Left(true)),
+        rename_provider: Some(OneOf::Right(RenameOptions {
+          prepare_provider: Some(true),
+          work_done_progress_options: WorkDoneProgressOptions::default(),
+        })),
+        execute_command_provider: Some(ExecuteCommandOptions {
+          commands: vec![ADD_TO_DICTIONARY_COMMAND.to_string()],
+          work_done_progress_options: WorkDoneProgressOptions::default(),
+        }),
         ..ServerCapabilities::default()
       },
     })
   }
 
+  async fn initialized(&self, _: InitializedParams) {
+    log::debug!("initialized");
+    if let Some(settings) = self.fetch_settings().await {
+      self.update_settings(settings).await;
+    }
+    self.watch_files().await;
+    self.index_workspace().await;
+  }
+
+  async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+    log::debug!("did_change_watched_files");
+    {
+      let files = self.files.read().await;
+      let mut workspace = self.workspace.write().await;
+      for change in params.changes {
+        if change.uri.path().ends_with(CONFIG_FILENAME)
+          || change.uri.path().ends_with(DICTIONARY_PATH)
+        {
+          *self.configs.write().await = HashMap::new();
+          continue;
+        }
+        let path = match change.uri.to_file_path() {
+          Ok(path) if is_markdown_file(&path) || is_image_file(&path) => path,
+          _other => continue,
+        };
+        // open documents are indexed from their contents in the editor instead
+        if files.contains_key(&change.uri) {
+          continue;
+        }
+        match change.typ {
+          FileChangeType::Deleted => workspace.remove(&path),
+          _other => workspace.update(&path),
+        }
+      }
+    }
+
+    // links in open documents may point to the files that changed
+    self.send_all_diagnostics().await;
+  }
+
+  async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+    log::debug!("did_change_configuration");
+    let settings = match self.fetch_settings().await {
+      Some(settings) => Some(settings),
+      None => Settings::from_value(params.settings),
+    };
+    if let Some(settings) = settings {
+      self.update_settings(settings).await;
+    }
+  }
+
   async fn did_open(&self, params: DidOpenTextDocumentParams) {
     let uri = params.text_document.uri;
     log::debug!("did_open: {}", uri);
@@ -132,17 +460,457 @@ impl LanguageServer for Backend {
 
     Ok(self.files.read().await.get(&uri).map(|document| {
       document
-        .actions(&uri, &params.context.diagnostics)
+        .actions(&uri, params.range, &params.context.diagnostics)
         .into_iter()
         .map(CodeActionOrCommand::CodeAction)
         .collect()
     }))
   }
 
+  async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+    let uri = params.text_document_position_params.text_document.uri;
+    log::debug!("hover: {}", uri);
+
+    let position = params.text_document_position_params.position;
+    let diagnostics = self
+      .diagnostics
+      .read()
+      .await
+      .get(&uri)
+      .cloned()
+      .unwrap_or_default()
+      .into_iter()
+      .filter(|diagnostic| diagnostic.range.start <= position && position <= diagnostic.range.end)
+      .collect::<Vec<Diagnostic>>();
+    let range = match diagnostics.first() {
+      Some(diagnostic) => diagnostic.range,
+      None => return Ok(None),
+    };
+
+    let path = uri.to_file_path().ok();
+    let config = self.document_config(path.as_deref()).await;
+    let contents = match self.files.read().await.get(&uri) {
+      Some(document) => {
+        let language = document.language(path.as_deref(), &config);
+        diagnostics
+          .iter()
+          .filter_map(|diagnostic| document.hover(diagnostic, &self.validators, language, &config))
+          .collect::<Vec<String>>()
+      }
+      None => return Ok(None),
+    };
+    if contents.is_empty() {
+      return Ok(None);
+    }
+
+    Ok(Some(Hover {
+      contents: HoverContents::Markup(MarkupContent {
+        kind: MarkupKind::Markdown,
+        value: contents.join("\n\n---\n\n"),
+      }),
+      range: Some(range),
+    }))
+  }
+
+  async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+    let uri = params.text_document_position.text_document.uri;
+    log::debug!("completion: {}", uri);
+
+    let position = params.text_document_position.position;
+    let (context, labels, headings) = match self.files.read().await.get(&uri) {
+      Some(document) => match document.completion_context(position) {
+        Some(context) => (context, document.labels(), document.headings()),
+        None => return Ok(None),
+      },
+      None => return Ok(None),
+    };
+
+    let items = match &context.kind {
+      CompletionKind::Label => completion::label_items(&labels, &context),
+      CompletionKind::Anchor(path) if path.is_empty() => {
+        completion::anchor_items(&headings, &context)
+      }
+      CompletionKind::Anchor(path) => {
+        let root = self.root.read().await.clone();
+        let headings = match workspace::resolve_link(&uri, root.as_deref(), path) {
+          Some(target) => self.target_headings(&target.uri).await.unwrap_or_default(),
+          None => Vec::new(),
+        };
+        completion::anchor_items(&headings, &context)
+      }
+      CompletionKind::Path => {
+        let path = match uri.to_file_path() {
+          Ok(path) => path,
+          Err(_) => return Ok(None),
+        };
+        let dir = match path.parent() {
+          Some(dir) => dir.to_path_buf(),
+          None => return Ok(None),
+        };
+        // files are completed from the workspace index, instead of walking the workspace again
+        let paths = self
+          .workspace
+          .read()
+          .await
+          .files()
+          .into_iter()
+          .filter(|file| *file != path)
+          .filter_map(|file| Some((relative_path(&dir, &file)?, is_image_file(&file))))
+          .collect::<Vec<(String, bool)>>();
+        completion::path_items(&paths, &context)
+      }
+    };
+    Ok(Some(CompletionResponse::Array(items)))
+  }
+
+  async fn goto_definition(
+    &self,
+    params: GotoDefinitionParams,
+  ) -> Result<Option<GotoDefinitionResponse>> {
+    let uri = params.text_document_position_params.text_document.uri;
+    log::debug!("goto_definition: {}", uri);
+
+    let position = params.text_document_position_params.position;
+    let (definition, destination) = match self.files.read().await.get(&uri) {
+      Some(document) => (document.definition(position), document.link_at(position)),
+      None => return Ok(None),
+    };
+    if let Some(range) = definition {
+      return Ok(Some(GotoDefinitionResponse::Scalar(Location::new(
+        uri, range,
+      ))));
+    }
+
+    let root = self.root.read().await.clone();
+    let target = match destination
+      .and_then(|destination| workspace::resolve_link(&uri, root.as_deref(), &destination))
+    {
+      Some(target) => target,
+      None => return Ok(None),
+    };
+    let exists = target
+      .uri
+      .to_file_path()
+      .map(|path| path.exists())
+      .unwrap_or(false);
+    if !exists && self.files.read().await.get(&target.uri).is_none() {
+      return Ok(None);
+    }
+
+    // links without a matching heading still open the file that they point to
+    let heading = match &target.fragment {
+      Some(fragment) => self
+        .target_headings(&target.uri)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .find(|heading| heading.slug == *fragment),
+      None => None,
+    };
+    let range = heading.map(|heading| heading.range).unwrap_or_default();
+    Ok(Some(GotoDefinitionResponse::Scalar(Location::new(
+      target.uri, range,
+    ))))
+  }
+
+  async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+    let uri = params.text_document_position.text_document.uri;
+    log::debug!("references: {}", uri);
+
+    let position = params.text_document_position.position;
+    let include_declaration = params.context.include_declaration;
+    Ok(self.files.read().await.get(&uri).map(|document| {
+      document
+        .references(position, include_declaration)
+        .into_iter()
+        .map(|range| Location::new(uri.clone(), range))
+        .collect()
+    }))
+  }
+
+  async fn document_link(&self, params: DocumentLinkParams) -> Result<Option<Vec<DocumentLink>>> {
+    let uri = params.text_document.uri;
+    log::debug!("document_link: {}", uri);
+
+    let root = self.root.read().await.clone();
+    let (targets, headings) = match self.files.read().await.get(&uri) {
+      Some(document) => (
+        document.link_targets(&uri, root.as_deref()),
+        document.headings(),
+      ),
+      None => return Ok(None),
+    };
+
+    let mut links: Vec<DocumentLink> = Vec::new();
+    for (range, target) in targets {
+      let mut target_uri = target.uri;
+      if let Some(fragment) = target.fragment {
+        let target_headings = if target_uri == uri {
+          headings.clone()
+        } else {
+          self.target_headings(&target_uri).await.unwrap_or_default()
+        };
+        // editors open files at a line when it's given as a "#L<line>" fragment
+        if let Some(heading) = target_headings
+          .iter()
+          .find(|heading| heading.slug == fragment)
+        {
+          target_uri.set_fragment(Some(&format!("L{}", heading.range.start.line + 1)));
+        }
+      }
+      links.push(DocumentLink {
+        range,
+        target: Some(target_uri),
+        tooltip: None,
+        data: None,
+      });
+    }
+    Ok(Some(links))
+  }
+
+  async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+    let uri = params.text_document.uri;
+    log::debug!("formatting: {}", uri);
+    Ok(self.format(&uri, None).await)
+  }
+
+  async fn range_formatting(
+    &self,
+    params: DocumentRangeFormattingParams,
+  ) -> Result<Option<Vec<TextEdit>>> {
+    let uri = params.text_document.uri;
+    log::debug!("range_formatting: {}", uri);
+    Ok(self.format(&uri, Some(params.range)).await)
+  }
+
+  async fn prepare_rename(
+    &self,
+    params: TextDocumentPositionParams,
+  ) -> Result<Option<PrepareRenameResponse>> {
+    let uri = params.text_document.uri;
+    log::debug!("prepare_rename: {}", uri);
+
+    let position = params.position;
+    Ok(
+      self
+        .files
+        .read()
+        .await
+        .get(&uri)
+        .and_then(|document| document.prepare_rename(position))
+        .map(PrepareRenameResponse::Range),
+    )
+  }
+
+  async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+    let uri = params.text_document_position.text_document.uri;
+    log::debug!("rename: {}", uri);
+
+    let position = params.text_document_position.position;
+    let new_name = params.new_name.trim();
+    if new_name.is_empty() || new_name.contains('\n') {
+      return Err(Error::invalid_params("The new name must be a single line"));
+    }
+
+    let files = self.files.read().await.clone();
+    let document = match files.get(&uri) {
+      Some(document) => document,
+      None => return Ok(None),
+    };
+    let (edit, slugs) = match document.rename_heading(position, new_name) {
+      Some(rename) => rename,
+      None if new_name.contains(&['[', ']'][..]) => {
+        return Err(Error::invalid_params("Labels can't contain brackets"));
+      }
+      None => {
+        return Ok(
+          document
+            .rename_label(position, new_name)
+            .map(|edits| workspace_edit(vec![(uri, edits)])),
+        );
+      }
+    };
+
+    let target = match uri.to_file_path() {
+      Ok(target) if !slugs.is_empty() => target,
+      _other => return Ok(Some(workspace_edit(vec![(uri, vec![edit])]))),
+    };
+
+    // anchors are updated in open documents first, and then in the files indexed on disk
+    let root = self.root.read().await.clone();
+    let mut changes: Vec<(Url, Vec<TextEdit>)> = Vec::new();
+    for (document_uri, document) in files.iter() {
+      let mut edits = document.anchor_edits(document_uri, root.as_deref(), &target, &slugs);
+      if *document_uri == uri {
+        edits.push(edit.clone());
+      }
+      if !edits.is_empty() {
+        changes.push((document_uri.clone(), edits));
+      }
+    }
+
+    let open_paths = files
+      .keys()
+      .filter_map(|uri| uri.to_file_path().ok())
+      .collect::<HashSet<PathBuf>>();
+    let paths = self
+      .workspace
+      .read()
+      .await
+      .paths()
+      .into_iter()
+      .filter(|path| !open_paths.contains(path))
+      .collect::<Vec<PathBuf>>();
+    let closed_changes = async_std::task::spawn_blocking(move || {
+      paths
+        .into_iter()
+        .filter_map(|path| {
+          let text = fs::read_to_string(&path).ok()?;
+          let uri = Url::from_file_path(&path).ok()?;
+          let edits = Document::new(text).anchor_edits(&uri, root.as_deref(), &target, &slugs);
+          if edits.is_empty() {
+            None
+          } else {
+            Some((uri, edits))
+          }
+        })
+        .collect::<Vec<(Url, Vec<TextEdit>)>>()
+    })
+    .await;
+    changes.extend(closed_changes);
+    Ok(Some(workspace_edit(changes)))
+  }
+
+  async fn document_symbol(
+    &self,
+    params: DocumentSymbolParams,
+  ) -> Result<Option<DocumentSymbolResponse>> {
+    let uri = params.text_document.uri;
+    log::debug!("document_symbol: {}", uri);
+
+    Ok(
+      self
+        .files
+        .read()
+        .await
+        .get(&uri)
+        .map(|document| DocumentSymbolResponse::Nested(document.symbols())),
+    )
+  }
+
+  async fn folding_range(&self, params: FoldingRangeParams) -> Result<Option<Vec<FoldingRange>>> {
+    let uri = params.text_document.uri;
+    log::debug!("folding_range: {}", uri);
+
+    Ok(
+      self
+        .files
+        .read()
+        .await
+        .get(&uri)
+        .map(|document| document.folding_ranges()),
+    )
+  }
+
+  async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+    let uri = params.text_document.uri;
+    log::debug!("code_lens: {}", uri);
+
+    let stats = match self.stats(&uri).await {
+      Some(stats) => stats,
+      None => return Ok(None),
+    };
+    Ok(
+      self
+        .files
+        .read()
+        .await
+        .get(&uri)
+        .map(|document| document.code_lenses(&stats)),
+    )
+  }
+
+  async fn execute_command(
+    &self,
+    params: ExecuteCommandParams,
+  ) -> Result<Option<serde_json::Value>> {
+    log::debug!("execute_command: {}", params.command);
+
+    if params.command == ADD_TO_DICTIONARY_COMMAND {
+      let mut arguments = params.arguments.into_iter();
+      let word = arguments
+        .next()
+        .and_then(|word| word.as_str().map(|word| word.to_string()));
+      let uri = arguments
+        .next()
+        .and_then(|uri| serde_json::from_value::<Url>(uri).ok());
+
+      if let Some(word) = word {
+        match self.dictionary_path(uri.as_ref()).await {
+          Some(path) => {
+            // configs include the dictionaries, so they're read again with the new word
+            *self.configs.write().await = HashMap::new();
+            if let Err(err) = dictionary::add_word(&path, &word) {
+              self
+                .client
+                .show_message(
+                  MessageType::Error,
+                  format!("Failed to add \"{}\" to {}: {}", word, path.display(), err),
+                )
+                .await;
+            }
+          }
+          None => {
+            self
+              .client
+              .show_message(MessageType::Error, "No dictionary file could be found")
+              .await;
+          }
+        }
+        self.send_all_diagnostics().await;
+      }
+    }
+
+    Ok(None)
+  }
+
   async fn did_close(&self, params: DidCloseTextDocumentParams) {
-    log::debug!("did_close: {}", params.text_document.uri);
-    let mut files = self.files.write().await;
-    *files = files.without(&params.text_document.uri);
+    let uri = params.text_document.uri;
+    log::debug!("did_close: {}", uri);
+    {
+      let mut files = self.files.write().await;
+      *files = files.without(&uri);
+      let mut diagnostics = self.diagnostics.write().await;
+      *diagnostics = diagnostics.without(&uri);
+      let mut stats = self.stats.write().await;
+      *stats = stats.without(&uri);
+    }
+
+    // the index may be out of date if the document was closed without saving it
+    if let Ok(path) = uri.to_file_path() {
+      self.workspace.write().await.update(&path);
+    }
+  }
+
+  async fn request_else(
+    &self,
+    method: &str,
+    params: Option<serde_json::Value>,
+  ) -> Result<Option<serde_json::Value>> {
+    log::debug!("request_else: {}", method);
+
+    if method != DOCUMENT_STATS_METHOD {
+      return Err(Error::method_not_found());
+    }
+    let params = params
+      .and_then(|params| serde_json::from_value::<TextDocumentParams>(params).ok())
+      .ok_or_else(|| Error::invalid_params("expected a textDocument"))?;
+    match self.stats(&params.text_document.uri).await {
+      Some(stats) => Ok(Some(
+        serde_json::to_value(stats).map_err(|_| Error::internal_error())?,
+      )),
+      None => Ok(None),
+    }
   }
 
   async fn shutdown(&self) -> Result<()> {
@@ -151,3 +919,40 @@ impl LanguageServer for Backend {
     Ok(())
   }
 }
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TextDocumentParams {
+  text_document: TextDocumentIdentifier,
+}
+
+fn workspace_edit(changes: Vec<(Url, Vec<TextEdit>)>) -> WorkspaceEdit {
+  WorkspaceEdit {
+    changes: Some(changes.into_iter().collect()),
+    ..WorkspaceEdit::default()
+  }
+}
+
+async fn cached_config(
+  configs: &RwLock<HashMap<PathBuf, Arc<Config>>>,
+  settings: &Settings,
+  path: Option<&Path>,
+  binaries: &Binaries,
+) -> Arc<Config> {
+  let config = Config::default().with_settings(settings);
+  let (path, dir) = match path.and_then(|path| Some((path, path.parent()?))) {
+    Some(paths) => paths,
+    None => return Arc::new(config.with_binaries(binaries)),
+  };
+
+  // configs and dictionaries are only read from disk once per directory, until they change
+  if let Some(config) = configs.read().await.get(dir) {
+    return Arc::clone(config);
+  }
+  let config = Arc::new(config.extend_for_file(path).with_binaries(binaries));
+  configs
+    .write()
+    .await
+    .insert(dir.to_path_buf(), Arc::clone(&config));
+  config
+}
@@ -0,0 +1,207 @@
+use crate::{
+  config::Config,
+  document::{diagnostic_suggestion, Document},
+  fix::{apply_suggestions, unified_diff},
+  link_validator::LinkValidator,
+  report::{write_report, FileReport, Format, Issue},
+  stats::DocumentStats,
+  validator::{Binaries, TextSuggestion, Validators},
+  workspace::{walk_dir, Workspace},
+};
+
+use serde::Serialize;
+use std::{
+  env, fs,
+  io::{stdout, Error, ErrorKind, Result},
+  path::{Path, PathBuf},
+};
+use url::Url;
+
+pub fn check(patterns: &[String], format: Format, binaries: &Binaries) -> Result<bool> {
+  let validators = Validators::default();
+  let mut workspace = Workspace::default();
+  // links starting with a slash are resolved relative to the directory that's being checked from
+  let root = env::current_dir().ok();
+  let mut reports: Vec<FileReport> = Vec::new();
+
+  for path in collect_files(patterns)? {
+    let config = file_config(&path, binaries)?;
+    let text = fs::read_to_string(&path)?;
+    let document = Document::new(text);
+    let language = document.language(Some(&path), &config);
+    let uri = fs::canonicalize(&path)
+      .ok()
+      .and_then(|path| Url::from_file_path(path).ok());
+    let mut link_validator = uri
+      .as_ref()
+      .map(|uri| LinkValidator::new(&mut workspace, uri, root.as_deref()));
+    let issues = document
+      .diagnostics(&validators, link_validator.as_mut(), language, &config)
+      .into_iter()
+      .map(|diagnostic| Issue {
+        fixes: document.fixes(&diagnostic),
+        diagnostic,
+      })
+      .collect();
+    reports.push(FileReport { path, issues });
+  }
+
+  write_report(format, &reports, &mut stdout().lock())?;
+  Ok(reports.iter().any(|report| !report.issues.is_empty()))
+}
+
+pub struct FixFilter {
+  pub rules: Vec<String>,
+  pub categories: Vec<String>,
+}
+
+impl FixFilter {
+  fn matches(&self, issue: &Issue) -> bool {
+    let rule_id = issue.rule_id().to_lowercase();
+    let rule_name = issue.rule_name().unwrap_or_default().to_lowercase();
+    let category = issue.category().to_lowercase();
+
+    let matches_rule = self.rules.is_empty()
+      || self.rules.iter().any(|rule| {
+        let rule = rule.to_lowercase();
+        // rule ids may be given without their numbered suffix, e.g. "TO_DO_HYPHEN"
+        rule == rule_id || rule == rule_name || rule_id.starts_with(&format!("{}.", rule))
+      });
+    let matches_category = self.categories.is_empty()
+      || self
+        .categories
+        .iter()
+        .any(|filter| filter.to_lowercase() == category);
+    matches_rule && matches_category
+  }
+}
+
+pub fn fix(
+  patterns: &[String],
+  filter: &FixFilter,
+  dry_run: bool,
+  binaries: &Binaries,
+) -> Result<()> {
+  let validators = Validators::default();
+
+  for path in collect_files(patterns)? {
+    let config = file_config(&path, binaries)?;
+    let text = fs::read_to_string(&path)?;
+    let document = Document::new(text.clone());
+    let language = document.language(Some(&path), &config);
+    let suggestions = document
+      .diagnostics(&validators, None, language, &config)
+      .into_iter()
+      .map(|diagnostic| Issue {
+        fixes: document.fixes(&diagnostic),
+        diagnostic,
+      })
+      .filter(|issue| filter.matches(issue))
+      .filter_map(|issue| diagnostic_suggestion(&issue.diagnostic))
+      .collect::<Vec<TextSuggestion>>();
+
+    let (output, applied) = apply_suggestions(&text, &suggestions);
+    if applied == 0 || output == text {
+      continue;
+    } else if dry_run {
+      print!(
+        "{}",
+        unified_diff(&path.to_string_lossy().replace('\\', "/"), &text, &output)
+      );
+    } else {
+      fs::write(&path, output)?;
+      println!("{}: applied {} fixes", path.display(), applied);
+    }
+  }
+
+  Ok(())
+}
+
+#[derive(Serialize)]
+struct FileStats<'a> {
+  path: &'a str,
+  #[serde(flatten)]
+  stats: &'a DocumentStats,
+}
+
+pub fn stats(patterns: &[String], json: bool, binaries: &Binaries) -> Result<()> {
+  let validators = Validators::default();
+
+  for path in collect_files(patterns)? {
+    let config = file_config(&path, binaries)?;
+    let text = fs::read_to_string(&path)?;
+    let document = Document::new(text);
+    let language = document.language(Some(&path), &config);
+    let stats = document.stats(&validators, language, &config);
+    let display_path = path.to_string_lossy().replace('\\', "/");
+
+    if json {
+      let file_stats = FileStats {
+        path: &display_path,
+        stats: &stats,
+      };
+      println!("{}", serde_json::to_string(&file_stats)?);
+      continue;
+    }
+
+    println!("{}: {}", display_path, stats.metrics.summary());
+    for section in &stats.sections {
+      println!(
+        "  {}:{}: {}: {}",
+        display_path,
+        section.range.start.line + 1,
+        section.heading.as_deref().unwrap_or("(introduction)"),
+        section.metrics.summary()
+      );
+    }
+  }
+
+  Ok(())
+}
+
+fn file_config(path: &Path, binaries: &Binaries) -> Result<Config> {
+  // relative paths, like "README.md", have an empty parent, so configs are found from the absolute path
+  let path = path.canonicalize()?;
+  Ok(Config::find(path.parent().unwrap_or(&path))?.with_binaries(binaries))
+}
+
+pub fn collect_files(patterns: &[String]) -> Result<Vec<PathBuf>> {
+  let mut files: Vec<PathBuf> = Vec::new();
+
+  for pattern in patterns {
+    let path = Path::new(pattern);
+    if path.is_dir() {
+      walk_dir(path, &mut files)?;
+    } else if path.is_file() {
+      files.push(path.to_path_buf());
+    } else {
+      let paths = glob::glob(pattern).map_err(|err| {
+        Error::new(
+          ErrorKind::InvalidInput,
+          format!("invalid pattern {}: {}", pattern, err),
+        )
+      })?;
+
+      let mut matched = false;
+      for path in paths.filter_map(|path| path.ok()) {
+        matched = true;
+        if path.is_dir() {
+          walk_dir(&path, &mut files)?;
+        } else {
+          files.push(path);
+        }
+      }
+
+      if !matched {
+        return Err(Error::new(
+          ErrorKind::NotFound,
+          format!("no files found matching {}", pattern),
+        ));
+      }
+    }
+  }
+
+  files.sort();
+  files.dedup();
+  Ok(files)
+}
@@ -0,0 +1,99 @@
+use crate::headings::Heading;
+
+use lspower::lsp::{CompletionItem, CompletionItemKind, CompletionTextEdit, Range, TextEdit};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompletionKind {
+  Path,
+  Anchor(String),
+  Label,
+}
+
+pub struct CompletionContext {
+  pub kind: CompletionKind,
+  pub prefix: String,
+  pub range: Range,
+}
+
+pub fn link_context(line: &str) -> Option<(CompletionKind, &str)> {
+  // the line is scanned instead of the tree, since links are usually incomplete while typing them
+  let destination = line.rfind("](").map(|index| index + 2);
+  let label = line.rfind("][").map(|index| index + 2);
+  match (destination, label) {
+    (Some(start), label) if label.map(|label| start > label).unwrap_or(true) => {
+      let text = &line[start..];
+      if text.contains(&[')', ' ', '\t'][..]) {
+        None
+      } else if let Some(index) = text.find('#') {
+        let path = text[..index].to_string();
+        Some((CompletionKind::Anchor(path), &text[index + 1..]))
+      } else {
+        Some((CompletionKind::Path, text))
+      }
+    }
+    (_, Some(start)) => {
+      let text = &line[start..];
+      if text.contains(']') {
+        None
+      } else {
+        Some((CompletionKind::Label, text))
+      }
+    }
+    _other => None,
+  }
+}
+
+pub fn path_items(paths: &[(String, bool)], context: &CompletionContext) -> Vec<CompletionItem> {
+  paths
+    .iter()
+    .map(|(path, image)| {
+      // relative paths are only written with a leading "./" if it's already been typed
+      let path = if context.prefix.starts_with("./") && !path.starts_with("../") {
+        format!("./{}", path)
+      } else {
+        path.clone()
+      };
+      CompletionItem {
+        kind: Some(CompletionItemKind::File),
+        detail: Some(if *image { "Image" } else { "Markdown" }.to_string()),
+        text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(
+          context.range,
+          path.clone(),
+        ))),
+        label: path,
+        ..CompletionItem::default()
+      }
+    })
+    .collect()
+}
+
+pub fn anchor_items(headings: &[Heading], context: &CompletionContext) -> Vec<CompletionItem> {
+  headings
+    .iter()
+    .map(|heading| CompletionItem {
+      label: heading.slug.clone(),
+      kind: Some(CompletionItemKind::Reference),
+      detail: Some(heading.text.clone()),
+      text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(
+        context.range,
+        heading.slug.clone(),
+      ))),
+      ..CompletionItem::default()
+    })
+    .collect()
+}
+
+pub fn label_items(labels: &[String], context: &CompletionContext) -> Vec<CompletionItem> {
+  labels
+    .iter()
+    .map(|label| CompletionItem {
+      label: label.clone(),
+      kind: Some(CompletionItemKind::Reference),
+      text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(
+        context.range,
+        label.clone(),
+      ))),
+      ..CompletionItem::default()
+    })
+    .collect()
+}
@@ -0,0 +1,414 @@
+use crate::{
+  dictionary::{find_workspace_dictionaries, user_dictionary_path, Dictionary},
+  language,
+  validator::{Binaries, BinaryPaths},
+};
+
+use glob::Pattern;
+use lspower::lsp::DiagnosticSeverity;
+use serde::Deserialize;
+use std::{
+  collections::BTreeMap,
+  fs,
+  io::{Error, ErrorKind, Result},
+  path::Path,
+};
+
+pub const CONFIG_FILENAME: &str = ".prosemd.toml";
+
+const DEFAULT_MAX_SENTENCE_LENGTH: usize = 30;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+  On,
+  Off,
+  Error,
+  Warning,
+  #[serde(alias = "information")]
+  Info,
+  Hint,
+}
+
+impl Level {
+  pub fn severity(self, default: DiagnosticSeverity) -> Option<DiagnosticSeverity> {
+    match self {
+      Level::On => Some(default),
+      Level::Off => None,
+      Level::Error => Some(DiagnosticSeverity::Error),
+      Level::Warning => Some(DiagnosticSeverity::Warning),
+      Level::Info => Some(DiagnosticSeverity::Information),
+      Level::Hint => Some(DiagnosticSeverity::Hint),
+    }
+  }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+  pub validate: bool,
+  pub disabled_rules: Vec<String>,
+  pub severity: BTreeMap<String, Level>,
+  pub debounce: u64,
+  pub language: Option<String>,
+  pub wrap: Option<usize>,
+  pub max_sentence_length: Option<usize>,
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    Self {
+      validate: true,
+      disabled_rules: Vec::new(),
+      severity: BTreeMap::new(),
+      debounce: 100,
+      language: None,
+      wrap: None,
+      max_sentence_length: None,
+    }
+  }
+}
+
+impl Settings {
+  pub fn from_value(value: serde_json::Value) -> Option<Self> {
+    // clients may either send the "prosemd" section by itself or all of their settings
+    let value = match value {
+      serde_json::Value::Object(mut map) if map.contains_key("prosemd") => map.remove("prosemd")?,
+      serde_json::Value::Null => return None,
+      value => value,
+    };
+    serde_json::from_value(value)
+      .map_err(|err| log::warn!("invalid settings: {}", err))
+      .ok()
+  }
+}
+
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+  #[serde(default)]
+  rules: BTreeMap<String, Level>,
+  language: Option<String>,
+  wrap: Option<usize>,
+  max_sentence_length: Option<usize>,
+  #[serde(default)]
+  binaries: BTreeMap<String, BinaryPaths>,
+}
+
+#[derive(Clone)]
+pub struct RulePattern {
+  pattern: String,
+  glob: Option<Pattern>,
+}
+
+impl RulePattern {
+  pub fn new(pattern: &str) -> Self {
+    let pattern = pattern.to_lowercase();
+    let glob = if pattern.contains(&['*', '?', '['][..]) {
+      Pattern::new(&pattern).ok()
+    } else {
+      None
+    };
+    Self { pattern, glob }
+  }
+
+  fn specificity(&self, rule_id: &str, categories: &[String]) -> Option<usize> {
+    let matches_id = match &self.glob {
+      Some(glob) if glob.matches(rule_id) => Some(3),
+      Some(_) => None,
+      // rule ids may be given without their numbered suffix, e.g. "TO_DO_HYPHEN"
+      None if rule_id == self.pattern || rule_id.starts_with(&format!("{}.", self.pattern)) => {
+        Some(4)
+      }
+      None => None,
+    };
+
+    matches_id.or_else(|| {
+      categories
+        .iter()
+        .filter_map(|category| match &self.glob {
+          Some(glob) if glob.matches(category) => Some(1),
+          None if *category == self.pattern => Some(2),
+          _other => None,
+        })
+        .max()
+    })
+  }
+
+  pub fn matches(&self, rule_id: &str, categories: &[&str]) -> bool {
+    let (rule_id, categories) = normalize_rule(rule_id, categories);
+    self.specificity(&rule_id, &categories).is_some()
+  }
+}
+
+fn parse_language(language: &str) -> &'static str {
+  language::normalize(language).unwrap_or_else(|| {
+    log::warn!("unsupported language \"{}\"", language);
+    language::DEFAULT_LANGUAGE
+  })
+}
+
+fn normalize_rule(rule_id: &str, categories: &[&str]) -> (String, Vec<String>) {
+  let categories = categories
+    .iter()
+    .map(|category| category.to_lowercase())
+    .collect();
+  (rule_id.to_lowercase(), categories)
+}
+
+#[derive(Clone)]
+struct RuleEntry {
+  pattern: RulePattern,
+  level: Level,
+}
+
+impl RuleEntry {
+  fn new(pattern: &str, level: Level) -> Self {
+    let pattern = RulePattern::new(pattern);
+    Self { pattern, level }
+  }
+}
+
+#[derive(Clone)]
+pub struct Config {
+  rules: Vec<RuleEntry>,
+  dictionary: Dictionary,
+  language: Option<&'static str>,
+  wrap: Option<usize>,
+  max_sentence_length: usize,
+  binaries: Binaries,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    let rules = vec![
+      // informally, "todo" is just as well accepted as "to-do"
+      RuleEntry::new("TO_DO_HYPHEN.2", Level::Off),
+      RuleEntry::new("TO_DO_HYPHEN.3", Level::Off),
+      // Wikipedia's style guide contains a few rules that are too opinionated
+      RuleEntry::new("wikipedia", Level::Off),
+      RuleEntry::new("typography", Level::Off),
+      // style rules are a matter of taste, so they have to be turned on explicitly
+      RuleEntry::new("style", Level::Off),
+    ];
+    Self {
+      rules,
+      dictionary: Dictionary::default(),
+      language: None,
+      wrap: None,
+      max_sentence_length: DEFAULT_MAX_SENTENCE_LENGTH,
+      binaries: Binaries::new(),
+    }
+  }
+}
+
+impl Config {
+  pub fn find(dir: &Path) -> Result<Self> {
+    Config::default().extend_from_dir(dir)
+  }
+
+  pub fn with_settings(mut self, settings: &Settings) -> Self {
+    self.rules.extend(
+      settings
+        .disabled_rules
+        .iter()
+        .map(|pattern| RuleEntry::new(pattern, Level::Off)),
+    );
+    self.rules.extend(
+      settings
+        .severity
+        .iter()
+        .map(|(pattern, level)| RuleEntry::new(pattern, *level)),
+    );
+    if let Some(language) = &settings.language {
+      self.language = Some(parse_language(language));
+    }
+    if settings.wrap.is_some() {
+      self.wrap = settings.wrap;
+    }
+    if let Some(max_sentence_length) = settings.max_sentence_length {
+      self.max_sentence_length = max_sentence_length;
+    }
+    self
+  }
+
+  pub fn extend_from_dir(mut self, dir: &Path) -> Result<Self> {
+    if let Some(path) = user_dictionary_path() {
+      self.dictionary.extend_from_file(&path);
+    }
+    for path in find_workspace_dictionaries(dir) {
+      self.dictionary.extend_from_file(&path);
+    }
+
+    let config_path = dir
+      .ancestors()
+      .map(|ancestor| ancestor.join(CONFIG_FILENAME))
+      .find(|path| path.is_file());
+    match config_path {
+      Some(path) => self.extend_from_file(&path),
+      None => Ok(self),
+    }
+  }
+
+  pub fn extend_for_file(self, path: &Path) -> Self {
+    let dir = path.parent().unwrap_or(path);
+    let fallback = self.clone();
+    self.extend_from_dir(dir).unwrap_or_else(|err| {
+      log::warn!("{}", err);
+      fallback
+    })
+  }
+
+  pub fn extend_from_file(mut self, path: &Path) -> Result<Self> {
+    let text = fs::read_to_string(path)?;
+    let file = toml::from_str::<ConfigFile>(&text).map_err(|err| {
+      Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid config {}: {}", path.display(), err),
+      )
+    })?;
+
+    self.rules.extend(
+      file
+        .rules
+        .iter()
+        .map(|(pattern, level)| RuleEntry::new(pattern, *level)),
+    );
+    if let Some(language) = &file.language {
+      self.language = Some(parse_language(language));
+    }
+    if file.wrap.is_some() {
+      self.wrap = file.wrap;
+    }
+    if let Some(max_sentence_length) = file.max_sentence_length {
+      self.max_sentence_length = max_sentence_length;
+    }
+
+    // binary paths are relative to the config file
+    let dir = path.parent().unwrap_or(path);
+    let binaries = file
+      .binaries
+      .into_iter()
+      .map(|(language, paths)| {
+        let paths = BinaryPaths {
+          rules: paths.rules.map(|rules| dir.join(rules)),
+          tokenizer: paths.tokenizer.map(|tokenizer| dir.join(tokenizer)),
+        };
+        (parse_language(&language), paths)
+      })
+      .collect();
+    Ok(self.with_binaries(&binaries))
+  }
+
+  pub fn with_binaries(mut self, binaries: &Binaries) -> Self {
+    for (language, paths) in binaries {
+      let entry = self.binaries.entry(*language).or_default();
+      if paths.rules.is_some() {
+        entry.rules = paths.rules.clone();
+      }
+      if paths.tokenizer.is_some() {
+        entry.tokenizer = paths.tokenizer.clone();
+      }
+    }
+    self
+  }
+
+  pub fn dictionary(&self) -> &Dictionary {
+    &self.dictionary
+  }
+
+  pub fn language(&self) -> Option<&'static str> {
+    self.language
+  }
+
+  pub fn wrap(&self) -> Option<usize> {
+    self.wrap
+  }
+
+  pub fn max_sentence_length(&self) -> usize {
+    self.max_sentence_length
+  }
+
+  pub fn binaries(&self, language: &str) -> BinaryPaths {
+    self.binaries.get(language).cloned().unwrap_or_default()
+  }
+
+  pub fn level(&self, rule_id: &str, categories: &[&str]) -> Level {
+    let (rule_id, categories) = normalize_rule(rule_id, categories);
+
+    // more specific matches win, and later entries override earlier ones of the same specificity
+    let mut level = Level::On;
+    let mut specificity = 0;
+    for entry in &self.rules {
+      if let Some(matched) = entry.pattern.specificity(&rule_id, &categories) {
+        if matched >= specificity {
+          level = entry.level;
+          specificity = matched;
+        }
+      }
+    }
+
+    level
+  }
+
+  pub fn severity(
+    &self,
+    rule_id: &str,
+    categories: &[&str],
+    default: DiagnosticSeverity,
+  ) -> Option<DiagnosticSeverity> {
+    self.level(rule_id, categories).severity(default)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::{env, fs};
+
+  #[test]
+  fn prefers_more_specific_rules() {
+    let mut config = Config::default();
+    config.rules.extend(vec![
+      RuleEntry::new("grammar", Level::Hint),
+      RuleEntry::new("gra*", Level::Error),
+      RuleEntry::new("SOME_RULE", Level::Warning),
+      RuleEntry::new("other_*", Level::Info),
+    ]);
+    assert_eq!(config.level("SOME_RULE.1", &["GRAMMAR"]), Level::Warning);
+    assert_eq!(config.level("OTHER_RULE", &["GRAMMAR"]), Level::Info);
+    assert_eq!(config.level("THIRD_RULE", &["GRAMMAR"]), Level::Hint);
+    assert_eq!(config.level("THIRD_RULE", &["GRAPHICS"]), Level::Error);
+    assert_eq!(config.level("THIRD_RULE", &["CASING"]), Level::On);
+    assert_eq!(config.level("TO_DO_HYPHEN.2", &[]), Level::Off);
+  }
+
+  #[test]
+  fn prefers_later_rules() {
+    let settings = Settings {
+      disabled_rules: vec!["style".to_string()],
+      ..Settings::default()
+    };
+    let mut config = Config::default().with_settings(&settings);
+    assert_eq!(config.level("LONG_SENTENCE", &["style"]), Level::Off);
+    config.rules.push(RuleEntry::new("style", Level::On));
+    assert_eq!(config.level("LONG_SENTENCE", &["style"]), Level::On);
+  }
+
+  #[test]
+  fn finds_config_files_in_parent_directories() {
+    let root = env::temp_dir().join(format!("prosemd-config-{}", std::process::id()));
+    let dir = root.join("docs").join("guide");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+      root.join(CONFIG_FILENAME),
+      "wrap = 80\n\n[rules]\nstyle = \"warning\"\n",
+    )
+    .unwrap();
+
+    let config = Config::find(&dir);
+    fs::remove_dir_all(&root).unwrap();
+    let config = config.unwrap();
+    assert_eq!(config.wrap(), Some(80));
+    assert_eq!(config.level("LONG_SENTENCE", &["style"]), Level::Warning);
+  }
+}
@@ -0,0 +1,70 @@
+use std::{
+  collections::HashSet,
+  env, fs,
+  io::{Result, Write},
+  path::{Path, PathBuf},
+};
+
+pub const DICTIONARY_PATH: &str = ".prosemd/dictionary.txt";
+pub const ADD_TO_DICTIONARY_COMMAND: &str = "prosemd.addToDictionary";
+
+#[derive(Clone, Default)]
+pub struct Dictionary {
+  words: HashSet<String>,
+}
+
+impl Dictionary {
+  pub fn extend_from_file(&mut self, path: &Path) {
+    if let Ok(text) = fs::read_to_string(path) {
+      self.words.extend(
+        text
+          .lines()
+          .map(|line| line.trim())
+          .filter(|line| !line.is_empty() && !line.starts_with('#'))
+          .map(|line| line.to_string()),
+      );
+    }
+  }
+
+  pub fn contains(&self, word: &str) -> bool {
+    let word = word.trim();
+    // lowercase entries match any casing, e.g. at the start of a sentence
+    self.words.contains(word) || self.words.contains(&word.to_lowercase())
+  }
+}
+
+pub fn user_dictionary_path() -> Option<PathBuf> {
+  let config_dir = env::var_os("XDG_CONFIG_HOME")
+    .map(PathBuf::from)
+    .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+    .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+  Some(config_dir.join("prosemd").join("dictionary.txt"))
+}
+
+pub fn find_workspace_dictionaries(dir: &Path) -> Vec<PathBuf> {
+  dir
+    .ancestors()
+    .map(|ancestor| ancestor.join(DICTIONARY_PATH))
+    .filter(|path| path.is_file())
+    .collect()
+}
+
+pub fn add_word(path: &Path, word: &str) -> Result<()> {
+  if let Some(dir) = path.parent() {
+    fs::create_dir_all(dir)?;
+  }
+
+  let needs_newline = match fs::read(path) {
+    Ok(contents) => !contents.is_empty() && !contents.ends_with(b"\n"),
+    Err(_) => false,
+  };
+
+  let mut file = fs::OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(path)?;
+  if needs_newline {
+    writeln!(file)?;
+  }
+  writeln!(file, "{}", word.trim())
+}
@@ -0,0 +1,175 @@
+use crate::{config::RulePattern, node_util};
+
+use tree_sitter::Tree;
+use xi_rope::Rope;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DirectiveKind {
+  Disable,
+  Enable,
+  DisableNextLine,
+  DisableFile,
+}
+
+struct Directive {
+  kind: DirectiveKind,
+  offset: usize,
+  line: usize,
+  rules: Vec<RulePattern>,
+}
+
+impl Directive {
+  fn applies_to(&self, rule_id: &str, categories: &[&str]) -> bool {
+    self.rules.is_empty()
+      || self
+        .rules
+        .iter()
+        .any(|pattern| pattern.matches(rule_id, categories))
+  }
+}
+
+pub struct Directives {
+  directives: Vec<Directive>,
+}
+
+impl Directives {
+  pub fn parse(rope: &Rope, tree: &Tree) -> Self {
+    let directives = node_util::find_nodes(tree, &[140 /* html block */, 205 /* html comment */])
+      .into_iter()
+      .filter_map(|node| {
+        let text = rope.slice_to_cow(node.start_byte()..node.end_byte());
+        let (kind, rules) = parse_comment(&text)?;
+        Some(Directive {
+          kind,
+          rules,
+          offset: node.end_byte(),
+          line: node.end_position().row,
+        })
+      })
+      .collect();
+    Self { directives }
+  }
+
+  pub fn is_suppressed(
+    &self,
+    offset: usize,
+    line: usize,
+    rule_id: &str,
+    categories: &[&str],
+  ) -> bool {
+    let mut suppressed = false;
+    for directive in &self.directives {
+      if !directive.applies_to(rule_id, categories) {
+        continue;
+      }
+
+      match directive.kind {
+        DirectiveKind::DisableFile => return true,
+        DirectiveKind::DisableNextLine if directive.line + 1 == line => return true,
+        DirectiveKind::Disable if directive.offset <= offset => suppressed = true,
+        DirectiveKind::Enable if directive.offset <= offset => suppressed = false,
+        _other => (),
+      }
+    }
+
+    suppressed
+  }
+}
+
+fn parse_comment(text: &str) -> Option<(DirectiveKind, Vec<RulePattern>)> {
+  let text = text.trim();
+  if !text.starts_with("<!--") || !text.ends_with("-->") || text.len() < 7 {
+    return None;
+  }
+
+  let mut words = text[4..text.len() - 3]
+    .split(|c: char| c.is_whitespace() || c == ',')
+    .filter(|word| !word.is_empty());
+  let kind = match words.next()? {
+    "prosemd-disable" => DirectiveKind::Disable,
+    "prosemd-enable" => DirectiveKind::Enable,
+    "prosemd-disable-next-line" => DirectiveKind::DisableNextLine,
+    "prosemd-disable-file" => DirectiveKind::DisableFile,
+    _other => return None,
+  };
+
+  Some((kind, words.map(RulePattern::new).collect()))
+}
+
+pub fn disable_comment(rule_id: &str) -> String {
+  format!("<!-- prosemd-disable {} -->", rule_id)
+}
+
+pub fn enable_comment(rule_id: &str) -> String {
+  format!("<!-- prosemd-enable {} -->", rule_id)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::parser;
+
+  // whether the first occurrence of a word in the text is suppressed for a rule
+  fn is_suppressed(text: &str, word: &str, rule_id: &str, categories: &[&str]) -> bool {
+    let tree = parser().parse(text, None).unwrap();
+    let directives = Directives::parse(&Rope::from(text), &tree);
+    let offset = text.find(word).unwrap();
+    let line = text[..offset].matches('\n').count();
+    directives.is_suppressed(offset, line, rule_id, categories)
+  }
+
+  #[test]
+  fn disables_rules_until_enabled() {
+    let text = "one\n\n<!-- prosemd-disable -->\n\ntwo\n\n<!-- prosemd-enable -->\n\nthree\n";
+    assert!(!is_suppressed(text, "one", "RULE", &[]));
+    assert!(is_suppressed(text, "two", "RULE", &[]));
+    assert!(!is_suppressed(text, "three", "RULE", &[]));
+
+    let text = "one <!-- prosemd-disable --> two <!-- prosemd-enable --> three\n";
+    assert!(!is_suppressed(text, "one", "RULE", &[]));
+    assert!(is_suppressed(text, "two", "RULE", &[]));
+    assert!(!is_suppressed(text, "three", "RULE", &[]));
+  }
+
+  #[test]
+  fn disables_matching_rules() {
+    let text = "<!-- prosemd-disable SOME_RULE, style, WIKI_* -->\n\ntext\n";
+    assert!(is_suppressed(text, "text", "SOME_RULE.2", &[]));
+    assert!(is_suppressed(text, "text", "OTHER_RULE", &["STYLE"]));
+    assert!(is_suppressed(text, "text", "WIKI_RULE", &[]));
+    assert!(!is_suppressed(text, "text", "OTHER_RULE", &["GRAMMAR"]));
+  }
+
+  #[test]
+  fn disables_the_next_line() {
+    let text = "<!-- prosemd-disable-next-line -->\none\ntwo\n\n<!-- prosemd-disable-next-line -->\n\nthree\n";
+    assert!(is_suppressed(text, "one", "RULE", &[]));
+    assert!(!is_suppressed(text, "two", "RULE", &[]));
+    assert!(!is_suppressed(text, "three", "RULE", &[]));
+
+    let text = "one <!-- prosemd-disable-next-line -->\ntwo\nthree\n";
+    assert!(!is_suppressed(text, "one", "RULE", &[]));
+    assert!(is_suppressed(text, "two", "RULE", &[]));
+    assert!(!is_suppressed(text, "three", "RULE", &[]));
+  }
+
+  #[test]
+  fn disables_the_whole_file() {
+    let text = "one\n\n<!-- prosemd-disable-file SOME_RULE -->\n";
+    assert!(is_suppressed(text, "one", "SOME_RULE", &[]));
+    assert!(!is_suppressed(text, "one", "OTHER_RULE", &[]));
+  }
+
+  #[test]
+  fn ignores_other_comments() {
+    let text = "<!-- a note -->\n\n<!-- prosemd-disabled -->\n\ntext\n";
+    assert!(!is_suppressed(text, "text", "RULE", &[]));
+  }
+
+  #[test]
+  fn creates_comments() {
+    let kind = |comment: String| parse_comment(&comment).map(|(kind, _)| kind);
+    assert_eq!(kind(disable_comment("RULE")), Some(DirectiveKind::Disable));
+    assert_eq!(kind(enable_comment("RULE")), Some(DirectiveKind::Enable));
+  }
+}
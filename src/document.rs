@@ -1,22 +1,42 @@
 use crate::{
-  node_util,
+  completion::{self, CompletionContext},
+  config::Config,
+  dictionary::ADD_TO_DICTIONARY_COMMAND,
+  directives::{disable_comment, enable_comment, Directives},
+  folding, format,
+  headings::{self, Heading},
+  hover, language,
+  link_validator::LinkValidator,
+  links::{self, LabelKind, LinkKind, References},
+  lint, node_util,
   parser::parser,
-  validator::{TextSuggestion, Validator},
+  rules::{self, BuiltinRule, UNDEFINED_REFERENCE, UNSUPPORTED_LANGUAGE, UNUSED_DEFINITION},
+  stats::{self, Counts, DocumentStats},
+  style, symbols, table,
+  validator::{Alternative, TextSuggestion, Validator, Validators},
+  workspace::{self, LinkTarget},
 };
 
 use lspower::lsp::{
-  CodeAction, CodeActionKind, Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range,
-  TextEdit, WorkspaceEdit,
+  CodeAction, CodeActionKind, CodeLens, Command, Diagnostic, DiagnosticSeverity, DiagnosticTag,
+  DocumentSymbol, FoldingRange, NumberOrString, Position, Range, TextEdit, WorkspaceEdit,
 };
 
 use std::{
   collections::hash_map::HashMap,
+  path::Path,
   sync::{Arc, Mutex},
 };
 use tree_sitter::{InputEdit, Parser, Point, Tree};
 use url::Url;
+use whatlang::Lang;
 use xi_rope::{rope::Utf16CodeUnitsMetric, Interval, Rope};
 
+pub struct Fix {
+  pub replacement: String,
+  pub edits: Vec<TextEdit>,
+}
+
 #[derive(Clone)]
 pub struct Document {
   version: i32,
@@ -57,70 +77,682 @@ impl Document {
     })
   }
 
-  pub fn diagnostics(&self, validator: &Validator) -> Vec<Diagnostic> {
-    node_util::find_text_nodes(&self.tree)
-      .iter()
-      .flat_map(|node| validator.suggest(&node_util::get_node_text(&self.rope, &node)))
-      .map(|suggestion| suggestion_to_diagnostic(&self.rope, &validator, suggestion))
-      .collect::<Vec<Diagnostic>>()
+  pub fn language(&self, path: Option<&Path>, config: &Config) -> &'static str {
+    // front matter takes precedence over the file name, which takes precedence over the config
+    language::from_front_matter(&self.rope.slice_to_cow(..))
+      .or_else(|| path.and_then(language::from_path))
+      .or_else(|| config.language())
+      .unwrap_or(language::DEFAULT_LANGUAGE)
   }
 
-  pub fn actions(&self, uri: &Url, diagnostics: &[Diagnostic]) -> Vec<CodeAction> {
+  pub fn diagnostics(
+    &self,
+    validators: &Validators,
+    link_validator: Option<&mut LinkValidator>,
+    language: &'static str,
+    config: &Config,
+  ) -> Vec<Diagnostic> {
+    let directives = Directives::parse(&self.rope, &self.tree);
+    let mut unsupported: Vec<Lang> = Vec::new();
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let check_style = style::is_enabled(config);
+
+    for node in node_util::find_text_nodes(&self.tree) {
+      let text = node_util::get_node_text(&self.rope, &node);
+      // languages may also lack rules when they weren't built in and none were configured
+      let validator = detect_language(&text.clean_text, language).and_then(|detected| {
+        match validators.get(detected, config.binaries(detected)) {
+          Some(validator) => Ok((detected, validator)),
+          None => Err(language::to_lang(detected)),
+        }
+      });
+      let (language, validator) = match validator {
+        Ok(validator) => validator,
+        Err(lang) => {
+          // text in languages without rules is skipped and only reported once
+          if !unsupported.contains(&lang) {
+            unsupported.push(lang);
+            diagnostics.extend(UNSUPPORTED_LANGUAGE.diagnostic(
+              &self.rope,
+              config,
+              &directives,
+              format!(
+                "This text appears to be in {}, which can't be checked",
+                lang.eng_name()
+              ),
+              node.start_byte(),
+              node.end_byte(),
+            ));
+          }
+          continue;
+        }
+      };
+
+      diagnostics.extend(
+        validator
+          .suggest(&text, config.dictionary())
+          .into_iter()
+          .filter_map(|suggestion| {
+            suggestion_to_diagnostic(&self.rope, &validator, config, &directives, suggestion)
+          }),
+      );
+
+      // style rules rely on english part-of-speech tags, and only apply to whole paragraphs
+      if check_style && language == "en" && node.kind_id() == 124 {
+        let sentences = validator.sentences(&text.clean_text);
+        diagnostics.extend(style::check(
+          &self.rope,
+          &text,
+          &sentences,
+          config,
+          &directives,
+        ));
+      }
+    }
+
+    diagnostics.extend(self.reference_diagnostics(config, &directives));
+    diagnostics.extend(lint::lint(&self.rope, &self.tree, config, &directives));
+    if let Some(link_validator) = link_validator {
+      diagnostics.extend(link_validator.validate(&self.rope, &self.tree, config, &directives));
+    }
     diagnostics
+  }
+
+  pub fn link_diagnostics(
+    &self,
+    link_validator: &mut LinkValidator,
+    config: &Config,
+  ) -> Vec<Diagnostic> {
+    let directives = Directives::parse(&self.rope, &self.tree);
+    link_validator.validate(&self.rope, &self.tree, config, &directives)
+  }
+
+  fn reference_diagnostics(&self, config: &Config, directives: &Directives) -> Vec<Diagnostic> {
+    let references = References::parse(&self.rope, &self.tree);
+    let diagnostic = |rule: &BuiltinRule, message: String, start: usize, end: usize| {
+      rule.diagnostic(&self.rope, config, directives, message, start, end)
+    };
+
+    let undefined = references
+      .references
       .iter()
-      .filter_map(|diagnostic| {
-        diagnostic_to_edits(&self.rope, &diagnostic).map(|edits| {
-          let title = format!(
-            "Autofix {}",
-            diagnostic
-              .source
-              .as_ref()
-              .unwrap_or(&"suggestion".to_string())
-          );
-          let mut changes = HashMap::new();
-          changes.insert(uri.clone(), edits);
-          CodeAction {
-            title,
-            kind: Some(CodeActionKind::QUICKFIX),
-            is_preferred: Some(true),
-            diagnostics: Some(vec![diagnostic.clone()]),
-            edit: Some(WorkspaceEdit::new(changes)),
-            disabled: None,
-            command: None,
-            data: None,
-          }
+      .filter(|reference| reference.explicit && references.definition(&reference.label).is_none())
+      .filter_map(|reference| {
+        let label = &reference.label;
+        let message = match label.kind {
+          LabelKind::Link => format!("Reference {} isn't defined", label.display()),
+          LabelKind::Footnote => format!("Footnote {} isn't defined", label.display()),
+        };
+        diagnostic(&UNDEFINED_REFERENCE, message, label.start, label.end)
+      });
+
+    let unused = references
+      .definitions
+      .iter()
+      .filter(|definition| references.references_to(&definition.label).next().is_none())
+      .filter_map(|definition| {
+        let label = &definition.label;
+        let message = match label.kind {
+          LabelKind::Link => format!("Reference {} is never used", label.display()),
+          LabelKind::Footnote => format!("Footnote {} is never used", label.display()),
+        };
+        let mut diagnostic = diagnostic(
+          &UNUSED_DEFINITION,
+          message,
+          definition.start,
+          definition.end,
+        )?;
+        diagnostic.tags = Some(vec![DiagnosticTag::Unnecessary]);
+        Some(diagnostic)
+      });
+
+    undefined.chain(unused).collect()
+  }
+
+  pub fn definition(&self, position: Position) -> Option<Range> {
+    let offset = position_to_offset(&self.rope, position);
+    let references = References::parse(&self.rope, &self.tree);
+    let label = references.label_at(offset)?;
+    let definition = references.definition(label)?;
+    Some(Range::new(
+      offset_to_position(&self.rope, definition.start),
+      offset_to_position(&self.rope, definition.end),
+    ))
+  }
+
+  pub fn references(&self, position: Position, include_declaration: bool) -> Vec<Range> {
+    let offset = position_to_offset(&self.rope, position);
+    let references = References::parse(&self.rope, &self.tree);
+    let label = match references.label_at(offset) {
+      Some(label) => label,
+      None => return Vec::new(),
+    };
+
+    let declaration = references
+      .definition(label)
+      .filter(|_| include_declaration)
+      .map(|definition| &definition.label);
+    declaration
+      .into_iter()
+      .chain(
+        references
+          .references_to(label)
+          .map(|reference| &reference.label),
+      )
+      .map(|label| {
+        Range::new(
+          offset_to_position(&self.rope, label.start),
+          offset_to_position(&self.rope, label.end),
+        )
+      })
+      .collect()
+  }
+
+  pub fn symbols(&self) -> Vec<DocumentSymbol> {
+    symbols::document_symbols(&self.rope, &self.tree)
+  }
+
+  pub fn folding_ranges(&self) -> Vec<FoldingRange> {
+    folding::folding_ranges(&self.rope, &self.tree)
+  }
+
+  pub fn headings(&self) -> Vec<Heading> {
+    headings::find_headings(&self.rope, &self.tree)
+  }
+
+  pub fn link_at(&self, position: Position) -> Option<String> {
+    let offset = position_to_offset(&self.rope, position);
+    links::find_links(&self.rope, &self.tree)
+      .into_iter()
+      .find(|link| link.contains(offset))
+      .map(|link| link.destination)
+  }
+
+  pub fn link_targets(&self, uri: &Url, root: Option<&Path>) -> Vec<(Range, LinkTarget)> {
+    links::find_links(&self.rope, &self.tree)
+      .into_iter()
+      .filter_map(|link| {
+        let destination = &link.destination;
+        let target = match link.kind {
+          LinkKind::EmailAutolink => LinkTarget {
+            uri: Url::parse(&format!("mailto:{}", destination)).ok()?,
+            fragment: None,
+          },
+          // extended autolinks, like "www.example.com", may leave out their scheme
+          LinkKind::Autolink => LinkTarget {
+            uri: Url::parse(destination)
+              .or_else(|_| Url::parse(&format!("http://{}", destination)))
+              .ok()?,
+            fragment: None,
+          },
+          _other => match Url::parse(destination) {
+            Ok(url) if url.scheme() != "file" => LinkTarget {
+              uri: url,
+              fragment: None,
+            },
+            _other => workspace::resolve_link(uri, root, destination)?,
+          },
+        };
+        let range = Range::new(
+          offset_to_position(&self.rope, link.destination_start),
+          offset_to_position(&self.rope, link.destination_end),
+        );
+        Some((range, target))
+      })
+      .collect()
+  }
+
+  pub fn completion_context(&self, position: Position) -> Option<CompletionContext> {
+    let offset = position_to_offset(&self.rope, position);
+    let line_start = self.rope.offset_of_line(self.rope.line_of_offset(offset));
+    let line = self.rope.slice_to_cow(line_start..offset);
+    let (kind, prefix) = completion::link_context(&line)?;
+    let start = offset - prefix.len();
+    Some(CompletionContext {
+      kind,
+      prefix: prefix.to_string(),
+      range: Range::new(offset_to_position(&self.rope, start), position),
+    })
+  }
+
+  pub fn labels(&self) -> Vec<String> {
+    let mut labels: Vec<String> = Vec::new();
+    for definition in References::parse(&self.rope, &self.tree).definitions {
+      let label = definition.label;
+      if label.kind == LabelKind::Link {
+        // labels are suggested as they're written in their definition, rather than normalized
+        let text = self.rope.slice_to_cow(label.start..label.end).into_owned();
+        if !labels.contains(&text) {
+          labels.push(text);
+        }
+      }
+    }
+    labels
+  }
+
+  pub fn prepare_rename(&self, position: Position) -> Option<Range> {
+    let offset = position_to_offset(&self.rope, position);
+    let (start, end) = match self.heading_at(offset) {
+      Some(heading) => (heading.content_start, heading.content_end),
+      None => References::parse(&self.rope, &self.tree)
+        .label_at(offset)?
+        .name_range(),
+    };
+    Some(Range::new(
+      offset_to_position(&self.rope, start),
+      offset_to_position(&self.rope, end),
+    ))
+  }
+
+  pub fn rename_heading(
+    &self,
+    position: Position,
+    new_name: &str,
+  ) -> Option<(TextEdit, HashMap<String, String>)> {
+    let offset = position_to_offset(&self.rope, position);
+    let heading = self.heading_at(offset)?;
+    let mut text = self.rope.slice_to_cow(..).into_owned();
+    text.replace_range(heading.content_start..heading.content_end, new_name);
+
+    // other headings' slugs may change too, when they were numbered because of duplicates
+    let headings = self.headings();
+    let renamed = Document::new(text).headings();
+    let slugs = headings
+      .into_iter()
+      .zip(renamed)
+      .filter(|(old, new)| old.slug != new.slug)
+      .map(|(old, new)| (old.slug, new.slug))
+      .collect::<HashMap<String, String>>();
+
+    let range = Range::new(
+      offset_to_position(&self.rope, heading.content_start),
+      offset_to_position(&self.rope, heading.content_end),
+    );
+    Some((TextEdit::new(range, new_name.to_string()), slugs))
+  }
+
+  pub fn rename_label(&self, position: Position, new_name: &str) -> Option<Vec<TextEdit>> {
+    let offset = position_to_offset(&self.rope, position);
+    let references = References::parse(&self.rope, &self.tree);
+    let label = references.label_at(offset)?;
+    let definitions = references
+      .definitions
+      .iter()
+      .filter(|definition| definition.label.matches(label))
+      .map(|definition| &definition.label);
+    let edits = definitions
+      .chain(
+        references
+          .references_to(label)
+          .map(|reference| &reference.label),
+      )
+      .map(|label| {
+        let (start, end) = label.name_range();
+        let range = Range::new(
+          offset_to_position(&self.rope, start),
+          offset_to_position(&self.rope, end),
+        );
+        TextEdit::new(range, new_name.to_string())
+      })
+      .collect();
+    Some(edits)
+  }
+
+  pub fn anchor_edits(
+    &self,
+    uri: &Url,
+    root: Option<&Path>,
+    target: &Path,
+    slugs: &HashMap<String, String>,
+  ) -> Vec<TextEdit> {
+    links::find_links(&self.rope, &self.tree)
+      .into_iter()
+      .filter_map(|link| {
+        let resolved = workspace::resolve_link(uri, root, &link.destination)?;
+        if resolved.uri.to_file_path().ok()? != target {
+          return None;
+        }
+        let slug = slugs.get(&resolved.fragment?)?;
+        let start = link.destination_start + link.destination.find('#')? + 1;
+        let range = Range::new(
+          offset_to_position(&self.rope, start),
+          offset_to_position(&self.rope, link.destination_end),
+        );
+        Some(TextEdit::new(range, slug.clone()))
+      })
+      .collect()
+  }
+
+  fn heading_at(&self, offset: usize) -> Option<Heading> {
+    self
+      .headings()
+      .into_iter()
+      .find(|heading| heading.content_start <= offset && offset <= heading.content_end)
+  }
+
+  pub fn hover(
+    &self,
+    diagnostic: &Diagnostic,
+    validators: &Validators,
+    language: &'static str,
+    config: &Config,
+  ) -> Option<String> {
+    let suggestion = diagnostic_suggestion(diagnostic)?;
+    if let Some(rule) = rules::builtin_rule(&suggestion.source) {
+      return Some(hover::builtin_rule_hover(rule, &suggestion));
+    }
+
+    // rules are looked up in the language that the diagnostic's text was checked in
+    let node = node_util::find_text_nodes(&self.tree)
+      .into_iter()
+      .find(|node| node.start_byte() <= suggestion.start && suggestion.start <= node.end_byte())?;
+    let text = node_util::get_node_text(&self.rope, &node);
+    let language = detect_language(&text.clean_text, language).ok()?;
+    let validator = validators.get(language, config.binaries(language))?;
+    let rule = validator.get_rule(&suggestion.source)?;
+    Some(hover::rule_hover(rule, &suggestion))
+  }
+
+  pub fn stats(
+    &self,
+    validators: &Validators,
+    language: &'static str,
+    config: &Config,
+  ) -> DocumentStats {
+    // sentences are split by the tokenizer of each paragraph's language, like for diagnostics
+    let paragraphs = node_util::find_text_nodes(&self.tree)
+      .into_iter()
+      .filter(|node| node.kind_id() == 124 /* paragraph */)
+      .filter_map(|node| {
+        let text = node_util::get_node_text(&self.rope, &node);
+        let language = detect_language(&text.clean_text, language).ok()?;
+        let validator = validators.get(language, config.binaries(language))?;
+        let sentences = validator.sentences(&text.clean_text);
+        Some((node.start_byte(), Counts::from_sentences(&sentences)))
+      })
+      .collect::<Vec<(usize, Counts)>>();
+    stats::document_stats(&self.rope, &self.headings(), &paragraphs)
+  }
+
+  pub fn code_lenses(&self, stats: &DocumentStats) -> Vec<CodeLens> {
+    let headings = self.headings();
+    stats
+      .sections
+      .iter()
+      .filter(|section| section.heading.is_some())
+      .zip(headings)
+      .map(|(section, heading)| CodeLens {
+        range: heading.range,
+        // the lenses only display the statistics, so their command doesn't do anything
+        command: Some(Command::new(section.metrics.summary(), String::new(), None)),
+        data: None,
+      })
+      .collect()
+  }
+
+  pub fn format(&self, config: &Config, range: Option<Range>) -> Vec<TextEdit> {
+    // range formatting only keeps the edits that lie within the range
+    let (start, end) = match range {
+      Some(range) => (
+        position_to_offset(&self.rope, range.start),
+        position_to_offset(&self.rope, range.end),
+      ),
+      None => (0, self.rope.len()),
+    };
+    format::format(&self.rope, &self.tree, config.wrap())
+      .into_iter()
+      .filter(|edit| start <= edit.start && edit.end <= end)
+      .map(|edit| {
+        let range = Range::new(
+          offset_to_position(&self.rope, edit.start),
+          offset_to_position(&self.rope, edit.end),
+        );
+        TextEdit::new(range, edit.text)
+      })
+      .collect()
+  }
+
+  pub fn fixes(&self, diagnostic: &Diagnostic) -> Vec<Fix> {
+    match diagnostic_suggestion(diagnostic) {
+      Some(suggestion) => suggestion
+        .alternatives
+        .iter()
+        .map(|alternative| Fix {
+          replacement: apply_alternative(&self.rope, &suggestion, alternative),
+          edits: alternative_to_edits(&self.rope, alternative),
         })
+        .collect(),
+      None => Vec::new(),
+    }
+  }
+
+  pub fn actions(&self, uri: &Url, range: Range, diagnostics: &[Diagnostic]) -> Vec<CodeAction> {
+    diagnostics
+      .iter()
+      .flat_map(|diagnostic| {
+        self
+          .fixes(diagnostic)
+          .into_iter()
+          .enumerate()
+          .map(move |(index, fix)| {
+            let title = if fix.replacement.is_empty() {
+              "Remove text".to_string()
+            } else {
+              format!("Replace with \"{}\"", fix.replacement)
+            };
+            let mut changes = HashMap::new();
+            changes.insert(uri.clone(), fix.edits);
+            CodeAction {
+              title,
+              kind: Some(CodeActionKind::QUICKFIX),
+              // only the top-ranked alternative is preferred
+              is_preferred: Some(index == 0),
+              diagnostics: Some(vec![diagnostic.clone()]),
+              edit: Some(WorkspaceEdit::new(changes)),
+              disabled: None,
+              command: None,
+              data: None,
+            }
+          })
       })
+      .chain(
+        diagnostics
+          .iter()
+          .filter_map(|diagnostic| self.dictionary_action(uri, diagnostic)),
+      )
+      .chain(
+        diagnostics
+          .iter()
+          .filter_map(|diagnostic| self.ignore_action(uri, diagnostic)),
+      )
+      .chain(self.table_action(uri, range))
       .collect()
   }
+
+  fn table_action(&self, uri: &Url, range: Range) -> Option<CodeAction> {
+    let start = position_to_offset(&self.rope, range.start);
+    let end = position_to_offset(&self.rope, range.end);
+    let table = node_util::find_nested_nodes(&self.tree, &[170 /* table */])
+      .into_iter()
+      .find(|node| node.start_byte() <= end && start <= node.end_byte())?;
+    // tables that are already aligned don't need an action
+    let edits = table::format_table(&self.rope, &table)
+      .into_iter()
+      .map(|edit| {
+        let range = Range::new(
+          offset_to_position(&self.rope, edit.start),
+          offset_to_position(&self.rope, edit.end),
+        );
+        TextEdit::new(range, edit.text)
+      })
+      .collect::<Vec<TextEdit>>();
+    if edits.is_empty() {
+      return None;
+    }
+
+    let mut changes = HashMap::new();
+    changes.insert(uri.clone(), edits);
+    Some(CodeAction {
+      title: "Format table".to_string(),
+      kind: Some(CodeActionKind::REFACTOR_REWRITE),
+      is_preferred: Some(false),
+      diagnostics: None,
+      edit: Some(WorkspaceEdit::new(changes)),
+      disabled: None,
+      command: None,
+      data: None,
+    })
+  }
+
+  fn dictionary_action(&self, uri: &Url, diagnostic: &Diagnostic) -> Option<CodeAction> {
+    if diagnostic.source.as_deref() != Some("misspelling") {
+      return None;
+    }
+
+    let suggestion = diagnostic_suggestion(diagnostic)?;
+    let word = self
+      .rope
+      .slice_to_cow(suggestion.start..suggestion.end)
+      .trim()
+      .to_string();
+    if word.is_empty() {
+      return None;
+    }
+
+    let title = format!("Add \"{}\" to dictionary", word);
+    Some(CodeAction {
+      title: title.clone(),
+      kind: Some(CodeActionKind::QUICKFIX),
+      is_preferred: Some(false),
+      diagnostics: Some(vec![diagnostic.clone()]),
+      edit: None,
+      disabled: None,
+      command: Some(Command {
+        title,
+        command: ADD_TO_DICTIONARY_COMMAND.to_string(),
+        arguments: Some(vec![
+          serde_json::Value::String(word),
+          serde_json::Value::String(uri.to_string()),
+        ]),
+      }),
+      data: None,
+    })
+  }
+
+  fn ignore_action(&self, uri: &Url, diagnostic: &Diagnostic) -> Option<CodeAction> {
+    let suggestion = diagnostic_suggestion(diagnostic)?;
+    let rule_id = rule_group_id(&suggestion.source);
+
+    // the directives wrap the top-level block, so that they don't split up paragraphs, lists, or tables
+    let root = self.tree.root_node();
+    let mut cursor = root.walk();
+    let block = root
+      .children(&mut cursor)
+      .find(|node| node.start_byte() <= suggestion.start && suggestion.start < node.end_byte())?;
+
+    let mut end = block.end_byte();
+    while end > block.start_byte()
+      && matches!(self.rope.slice_to_cow(end - 1..end).as_ref(), "\n" | "\r")
+    {
+      end -= 1;
+    }
+
+    let start = Position::new(block.start_position().row as u32, 0);
+    let end = offset_to_position(&self.rope, end);
+    let edits = vec![
+      TextEdit::new(
+        Range::new(start, start),
+        format!("{}\n", disable_comment(rule_id)),
+      ),
+      TextEdit::new(
+        Range::new(end, end),
+        format!("\n{}", enable_comment(rule_id)),
+      ),
+    ];
+
+    let mut changes = HashMap::new();
+    changes.insert(uri.clone(), edits);
+    Some(CodeAction {
+      title: format!("Ignore this rule here ({})", rule_id),
+      kind: Some(CodeActionKind::QUICKFIX),
+      is_preferred: Some(false),
+      diagnostics: Some(vec![diagnostic.clone()]),
+      edit: Some(WorkspaceEdit::new(changes)),
+      disabled: None,
+      command: None,
+      data: None,
+    })
+  }
 }
 
-fn diagnostic_to_edits(rope: &Rope, diagnostic: &Diagnostic) -> Option<Vec<TextEdit>> {
+fn rule_group_id(rule_id: &str) -> &str {
+  // rules in a group share an id and are only distinguished by a numbered suffix, e.g. ".2"
+  match rule_id.rsplit_once('.') {
+    Some((group, index)) if index.chars().all(|c| c.is_ascii_digit()) => group,
+    _other => rule_id,
+  }
+}
+
+pub fn diagnostic_suggestion(diagnostic: &Diagnostic) -> Option<TextSuggestion> {
   diagnostic
     .data
     .as_ref()
     .map(|data| serde_json::from_value::<TextSuggestion>(data.clone()).ok())
     .flatten()
-    .map(|edit| {
-      edit
-        .replacements
-        .into_iter()
-        .map(|replacement| {
-          let range = Range::new(
-            offset_to_position(&rope, replacement.start),
-            offset_to_position(&rope, replacement.end),
-          );
-          TextEdit::new(range, replacement.replacement)
-        })
-        .collect()
+}
+
+fn alternative_to_edits(rope: &Rope, alternative: &Alternative) -> Vec<TextEdit> {
+  alternative
+    .replacements
+    .iter()
+    .map(|replacement| {
+      let range = Range::new(
+        offset_to_position(rope, replacement.start),
+        offset_to_position(rope, replacement.end),
+      );
+      TextEdit::new(range, replacement.replacement.clone())
     })
+    .collect()
+}
+
+fn apply_alternative(
+  rope: &Rope,
+  suggestion: &TextSuggestion,
+  alternative: &Alternative,
+) -> String {
+  let (start, end) = (suggestion.start, suggestion.end);
+  let mut text = rope.slice_to_cow(start..end).into_owned();
+  let mut replacements = alternative
+    .replacements
+    .iter()
+    .filter(|replacement| replacement.start >= start && replacement.end <= end)
+    .collect::<Vec<_>>();
+  replacements.sort_by_key(|replacement| std::cmp::Reverse(replacement.start));
+
+  for replacement in replacements {
+    let range = (replacement.start - start)..(replacement.end - start);
+    if text.is_char_boundary(range.start) && text.is_char_boundary(range.end) {
+      text.replace_range(range, &replacement.replacement);
+    }
+  }
+
+  text
+}
+
+fn detect_language(text: &str, default: &'static str) -> Result<&'static str, Lang> {
+  match language::detect(text) {
+    Some(lang) => language::from_lang(lang).ok_or(lang),
+    None => Ok(default),
+  }
 }
 
 fn suggestion_to_diagnostic(
   rope: &Rope,
   validator: &Validator,
+  config: &Config,
+  directives: &Directives,
   suggestion: TextSuggestion,
-) -> Diagnostic {
+) -> Option<Diagnostic> {
   let range = Range::new(
     offset_to_position(&rope, suggestion.start),
     offset_to_position(&rope, suggestion.end),
@@ -137,19 +769,26 @@ fn suggestion_to_diagnostic(
 
   if let Some(rule) = validator.get_rule(&suggestion.source) {
     diagnostic.code = Some(NumberOrString::String(rule.name().to_string()));
-    if let Some(category_id) = rule.category_type() {
-      diagnostic.source = Some(category_id.to_string());
-      diagnostic.severity = Some(match category_id {
-        "grammar" => DiagnosticSeverity::Error,
-        "inconsistency" => DiagnosticSeverity::Error,
-        "misspelling" => DiagnosticSeverity::Warning,
-        "typographical" => DiagnosticSeverity::Warning,
-        _other => DiagnosticSeverity::Warning,
-      });
+    let category_type = rule.category_type().unwrap_or_default();
+    let default_severity = match category_type {
+      "grammar" => DiagnosticSeverity::Error,
+      "inconsistency" => DiagnosticSeverity::Error,
+      "misspelling" => DiagnosticSeverity::Warning,
+      "typographical" => DiagnosticSeverity::Warning,
+      _other => DiagnosticSeverity::Warning,
+    };
+    if !category_type.is_empty() {
+      diagnostic.source = Some(category_type.to_string());
+    }
+    let categories = [rule.category_id(), category_type];
+    diagnostic.severity = Some(config.severity(rule.id(), &categories, default_severity)?);
+    let line = range.start.line as usize;
+    if directives.is_suppressed(suggestion.start, line, rule.id(), &categories) {
+      return None;
     }
   }
 
-  diagnostic
+  Some(diagnostic)
 }
 
 fn edit_range(doc: &Document, version: i32, range: Range, text: String) -> Document {
@@ -212,9 +851,11 @@ fn offset_to_point(rope: &Rope, offset: usize) -> Point {
   Point { row, column }
 }
 
-fn offset_to_position(rope: &Rope, offset: usize) -> Position {
+pub fn offset_to_position(rope: &Rope, offset: usize) -> Position {
+  // positions count utf-16 code units, unlike the byte offsets of the rope
   let row = rope.line_of_offset(offset);
-  let column = offset - rope.offset_of_line(row);
+  let column = rope.count::<Utf16CodeUnitsMetric>(offset)
+    - rope.count::<Utf16CodeUnitsMetric>(rope.offset_of_line(row));
   Position::new(row as u32, column as u32)
 }
 
@@ -0,0 +1,190 @@
+use crate::validator::{Replacement, TextSuggestion};
+
+pub fn apply_suggestions(text: &str, suggestions: &[TextSuggestion]) -> (String, usize) {
+  // only suggestions with a single unambiguous alternative are applied automatically
+  let mut suggestions = suggestions
+    .iter()
+    .filter(|suggestion| suggestion.alternatives.len() == 1)
+    .collect::<Vec<&TextSuggestion>>();
+  suggestions.sort_by_key(|suggestion| (suggestion.start, suggestion.end));
+
+  // overlapping suggestions are skipped, since their edits can't be applied together safely
+  let mut replacements: Vec<&Replacement> = Vec::new();
+  let mut applied = 0;
+  let mut last_end = 0;
+  for suggestion in suggestions {
+    if suggestion.start >= last_end {
+      replacements.extend(suggestion.alternatives[0].replacements.iter());
+      last_end = suggestion.end;
+      applied += 1;
+    }
+  }
+
+  let mut output = text.to_string();
+  replacements.sort_by_key(|replacement| std::cmp::Reverse(replacement.start));
+  for replacement in replacements {
+    let range = replacement.start..replacement.end;
+    if range.end <= output.len()
+      && output.is_char_boundary(range.start)
+      && output.is_char_boundary(range.end)
+    {
+      output.replace_range(range, &replacement.replacement);
+    }
+  }
+
+  (output, applied)
+}
+
+enum Line<'a> {
+  Context(&'a str),
+  Removed(&'a str),
+  Added(&'a str),
+}
+
+pub fn unified_diff(path: &str, old: &str, new: &str) -> String {
+  const CONTEXT: usize = 3;
+
+  // a trailing newline would otherwise show up as an extra empty line
+  let (old, new) = (
+    old.strip_suffix('\n').unwrap_or(old),
+    new.strip_suffix('\n').unwrap_or(new),
+  );
+  let lines = diff::lines(old, new)
+    .into_iter()
+    .map(|line| match line {
+      diff::Result::Both(line, _) => Line::Context(line),
+      diff::Result::Left(line) => Line::Removed(line),
+      diff::Result::Right(line) => Line::Added(line),
+    })
+    .collect::<Vec<Line>>();
+
+  let mut hunks: Vec<(usize, usize)> = Vec::new();
+  for (index, line) in lines.iter().enumerate() {
+    if let Line::Context(_) = line {
+      continue;
+    }
+    let start = index.saturating_sub(CONTEXT);
+    let end = (index + CONTEXT + 1).min(lines.len());
+    match hunks.last_mut() {
+      Some(hunk) if hunk.1 >= start => hunk.1 = end,
+      _other => hunks.push((start, end)),
+    }
+  }
+
+  if hunks.is_empty() {
+    return String::new();
+  }
+
+  let mut output = format!("--- a/{}\n+++ b/{}\n", path, path);
+  let mut old_line = 0;
+  let mut new_line = 0;
+  let mut index = 0;
+  for (start, end) in hunks {
+    while index < start {
+      match lines[index] {
+        Line::Context(_) => {
+          old_line += 1;
+          new_line += 1;
+        }
+        Line::Removed(_) => old_line += 1,
+        Line::Added(_) => new_line += 1,
+      }
+      index += 1;
+    }
+
+    let hunk = &lines[start..end];
+    let old_count = hunk
+      .iter()
+      .filter(|line| !matches!(line, Line::Added(_)))
+      .count();
+    let new_count = hunk
+      .iter()
+      .filter(|line| !matches!(line, Line::Removed(_)))
+      .count();
+
+    output.push_str(&format!(
+      "@@ -{},{} +{},{} @@\n",
+      hunk_start(old_line, old_count),
+      old_count,
+      hunk_start(new_line, new_count),
+      new_count
+    ));
+    for line in hunk {
+      let (prefix, text) = match line {
+        Line::Context(text) => (' ', text),
+        Line::Removed(text) => ('-', text),
+        Line::Added(text) => ('+', text),
+      };
+      output.push(prefix);
+      output.push_str(text);
+      output.push('\n');
+    }
+  }
+
+  output
+}
+
+fn hunk_start(line: usize, count: usize) -> usize {
+  if count == 0 {
+    line
+  } else {
+    line + 1
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::validator::Alternative;
+
+  fn suggestion(start: usize, end: usize, replacements: &[&str]) -> TextSuggestion {
+    TextSuggestion {
+      source: "TEST".to_string(),
+      message: String::new(),
+      alternatives: replacements
+        .iter()
+        .map(|replacement| Alternative {
+          text: replacement.to_string(),
+          replacements: vec![Replacement {
+            replacement: replacement.to_string(),
+            start,
+            end,
+          }],
+        })
+        .collect(),
+      start,
+      end,
+    }
+  }
+
+  #[test]
+  fn applies_unambiguous_suggestions() {
+    let suggestions = [
+      suggestion(0, 3, &["The"]),
+      suggestion(9, 13, &["cat", "car"]),
+      suggestion(14, 17, &["is"]),
+    ];
+    assert_eq!(
+      apply_suggestions("the big  catt are here", &suggestions),
+      ("The big  catt is here".to_string(), 2)
+    );
+  }
+
+  #[test]
+  fn skips_overlapping_suggestions() {
+    let suggestions = [suggestion(4, 9, &["a b"]), suggestion(0, 6, &["x"])];
+    assert_eq!(
+      apply_suggestions("one two three", &suggestions),
+      ("xo three".to_string(), 1)
+    );
+  }
+
+  #[test]
+  fn prints_unified_diffs() {
+    assert_eq!(unified_diff("a.md", "same\n", "same\n"), "");
+    assert_eq!(
+      unified_diff("a.md", "one\ntwo\nthree\n", "one\n2\nthree\n"),
+      "--- a/a.md\n+++ b/a.md\n@@ -1,3 +1,3 @@\n one\n-two\n+2\n three\n"
+    );
+  }
+}
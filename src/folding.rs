@@ -0,0 +1,99 @@
+use crate::node_util;
+
+use lspower::lsp::{FoldingRange, FoldingRangeKind};
+use tree_sitter::{Node, Tree};
+use xi_rope::Rope;
+
+pub fn folding_ranges(rope: &Rope, tree: &Tree) -> Vec<FoldingRange> {
+  let mut ranges: Vec<FoldingRange> = Vec::new();
+
+  let front_matter = node_util::find_front_matter(tree);
+  if let Some(node) = front_matter {
+    ranges.extend(folding_range(0, end_line(&node), FoldingRangeKind::Region));
+  }
+
+  let headings = node_util::find_nodes(tree, &[129 /* setext heading */, 132 /* atx heading */])
+    .into_iter()
+    .filter(|node| Some(*node) != front_matter)
+    .filter_map(|node| Some((node_util::heading_level(&node)?, node)))
+    .collect::<Vec<(usize, Node)>>();
+  for (index, (level, node)) in headings.iter().enumerate() {
+    // sections fold up to the next heading of the same or a higher level
+    let start = node.start_byte();
+    let end = headings[index + 1..]
+      .iter()
+      .find(|(next_level, _)| next_level <= level)
+      .map(|(_, next)| next.start_byte())
+      .unwrap_or_else(|| rope.len());
+    let text = rope.slice_to_cow(start..end);
+    let end_line = rope.line_of_offset(start + text.trim_end().len());
+    if end_line <= node.end_position().row {
+      continue;
+    }
+    ranges.extend(folding_range(
+      node.start_position().row,
+      end_line,
+      FoldingRangeKind::Region,
+    ));
+  }
+
+  let blocks = node_util::find_nested_nodes(
+    tree,
+    &[
+      134, /* indented code block */
+      136, /* fenced code block */
+      140, /* html block */
+      154, /* block quote */
+      160, /* list item */
+      161, /* task list item */
+    ],
+  );
+  for node in blocks {
+    let kind = if node.kind_id() == 140 /* html block */
+      && rope
+        .slice_to_cow(node.start_byte()..node.end_byte())
+        .trim_start()
+        .starts_with("<!--")
+    {
+      FoldingRangeKind::Comment
+    } else {
+      FoldingRangeKind::Region
+    };
+    ranges.extend(folding_range(
+      node.start_position().row,
+      end_line(&node),
+      kind,
+    ));
+  }
+
+  ranges.sort_by_key(|range| (range.start_line, std::cmp::Reverse(range.end_line)));
+  ranges
+}
+
+fn end_line(node: &Node) -> usize {
+  // nodes that end at the start of a line don't include that line
+  let end = node.end_position();
+  if end.column == 0 && end.row > node.start_position().row {
+    end.row - 1
+  } else {
+    end.row
+  }
+}
+
+fn folding_range(
+  start_line: usize,
+  end_line: usize,
+  kind: FoldingRangeKind,
+) -> Option<FoldingRange> {
+  if end_line > start_line {
+    Some(FoldingRange {
+      start_line: start_line as u32,
+      start_character: None,
+      end_line: end_line as u32,
+      end_character: None,
+      kind: Some(kind),
+    })
+  } else {
+    None
+  }
+}
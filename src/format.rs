@@ -0,0 +1,494 @@
+use crate::{node_util, table};
+
+use tree_sitter::{Node, Tree};
+use unicode_width::UnicodeWidthStr;
+use xi_rope::Rope;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edit {
+  pub start: usize,
+  pub end: usize,
+  pub text: String,
+}
+
+impl Edit {
+  pub fn new(start: usize, end: usize, text: String) -> Self {
+    Self { start, end, text }
+  }
+
+  pub fn minimal(rope: &Rope, start: usize, end: usize, text: String) -> Option<Self> {
+    // common prefixes and suffixes are trimmed, so that edits only replace what has changed
+    let old = rope.slice_to_cow(start..end);
+    let prefix = common_prefix(&old, &text);
+    let suffix = common_suffix(&old[prefix..], &text[prefix..]);
+    if prefix == old.len() && prefix == text.len() {
+      None
+    } else {
+      Some(Self::new(
+        start + prefix,
+        end - suffix,
+        text[prefix..text.len() - suffix].to_string(),
+      ))
+    }
+  }
+}
+
+pub fn format(rope: &Rope, tree: &Tree, wrap: Option<usize>) -> Vec<Edit> {
+  let mut edits: Vec<Edit> = Vec::new();
+  format_headings(rope, tree, &mut edits);
+  format_list_markers(rope, tree, &mut edits);
+  format_emphasis(rope, tree, &mut edits);
+  format_fences(rope, tree, &mut edits);
+  for node in node_util::find_nested_nodes(tree, &[170 /* table */]) {
+    edits.extend(table::format_table(rope, &node));
+  }
+  if let Some(width) = wrap {
+    wrap_paragraphs(rope, tree, width, &mut edits);
+  }
+
+  // edits must not overlap, so overlapping edits are left for the next time a document's formatted
+  edits.sort_by_key(|edit| (edit.start, edit.end));
+  let mut end = 0;
+  edits.retain(|edit| {
+    let is_disjoint = edit.start >= end;
+    if is_disjoint {
+      end = edit.end;
+    }
+    is_disjoint
+  });
+  edits
+}
+
+fn format_headings(rope: &Rope, tree: &Tree, edits: &mut Vec<Edit>) {
+  let front_matter = node_util::find_front_matter(tree);
+  let headings = node_util::find_nodes(tree, &[129 /* setext heading */, 132 /* atx heading */]);
+  for node in headings
+    .into_iter()
+    .filter(|node| Some(*node) != front_matter)
+  {
+    let (level, content) = match (
+      node_util::heading_level(&node),
+      node_util::heading_content(&node),
+    ) {
+      (Some(level), Some(content)) => (level, content),
+      _other => continue,
+    };
+    let (start, end) = trimmed_range(rope, &content);
+    if start >= end {
+      continue;
+    }
+
+    if node.kind_id() == 132 {
+      // atx headings are separated from their marker by one space and have no closing sequence
+      let marker_end = content.start_byte();
+      edits.extend(Edit::minimal(rope, marker_end, start, " ".to_string()));
+      edits.extend(Edit::minimal(rope, end, node.end_byte(), String::new()));
+    } else {
+      // setext headings are converted to atx headings, which are also used for levels beyond two
+      edits.push(Edit::new(start, start, format!("{} ", "#".repeat(level))));
+      let mut cursor = content.walk();
+      let line_breaks = content
+        .children(&mut cursor)
+        .filter(|child| child.kind_id() == 111 /* soft line break */);
+      for line_break in line_breaks {
+        let break_start = trimmed_end(rope, content.start_byte(), line_break.start_byte());
+        edits.push(Edit::new(
+          break_start,
+          line_break.end_byte(),
+          " ".to_string(),
+        ));
+      }
+      edits.push(Edit::new(end, node.end_byte(), String::new()));
+    }
+  }
+}
+
+const LIST_KINDS: [u16; 2] = [156 /* tight list */, 158 /* loose list */];
+
+pub fn expected_bullets<'a>(rope: &Rope, tree: &'a Tree) -> Vec<(Node<'a>, char)> {
+  // bullet lists use the document's first marker, which the `LIST_MARKER_STYLE` rule expects too
+  let mut bullet: Option<char> = None;
+  let mut lists: Vec<(Node, char)> = Vec::new();
+  for list in node_util::find_nested_nodes(tree, &LIST_KINDS) {
+    let marker = match list_bullet(rope, &list) {
+      Some(marker) => marker,
+      None => continue,
+    };
+    // a list that directly follows another list is only separate because of its different marker
+    let previous = list
+      .prev_named_sibling()
+      .filter(|sibling| LIST_KINDS.contains(&sibling.kind_id()))
+      .and_then(|sibling| {
+        lists
+          .iter()
+          .find(|(node, _)| *node == sibling)
+          .map(|(_, marker)| *marker)
+      });
+    let expected = match previous {
+      Some(previous) if marker != previous => marker,
+      Some('-') => '*',
+      Some(_) => '-',
+      None => *bullet.get_or_insert(marker),
+    };
+    lists.push((list, expected));
+  }
+  lists
+}
+
+fn format_list_markers(rope: &Rope, tree: &Tree, edits: &mut Vec<Edit>) {
+  let bullets = expected_bullets(rope, tree);
+  for list in node_util::find_nested_nodes(tree, &LIST_KINDS) {
+    let expected = bullets
+      .iter()
+      .find(|(node, _)| *node == list)
+      .map(|(_, bullet)| *bullet);
+
+    let mut number: Option<usize> = None;
+    let mut cursor = list.walk();
+    for item in list.children(&mut cursor) {
+      let marker = match node_util::find_child(&item, 48 /* list marker */) {
+        Some(marker) => marker,
+        None => continue,
+      };
+      let text = node_text(rope, &marker);
+      let start = marker.start_byte();
+      match (text.chars().next(), expected) {
+        (Some(c), Some(expected)) if matches!(c, '-' | '*' | '+') && c != expected => {
+          edits.push(Edit::new(start, start + 1, expected.to_string()));
+        }
+        (Some(c), _) if c.is_ascii_digit() => {
+          // ordered lists are numbered consecutively, starting from their first item's number
+          let digits = text.chars().take_while(|c| c.is_ascii_digit()).count();
+          let expected = match number {
+            Some(previous) => previous + 1,
+            None => text[..digits].parse::<usize>().unwrap_or(1),
+          };
+          number = Some(expected);
+          edits.extend(Edit::minimal(
+            rope,
+            start,
+            start + digits,
+            expected.to_string(),
+          ));
+        }
+        _other => (),
+      }
+    }
+  }
+}
+
+fn list_bullet(rope: &Rope, list: &Node) -> Option<char> {
+  // items of the same list always share their bullet marker
+  let item = list.named_child(0)?;
+  let marker = node_util::find_child(&item, 48 /* list marker */)?;
+  node_text(rope, &marker)
+    .chars()
+    .next()
+    .filter(|c| matches!(c, '-' | '*' | '+'))
+}
+
+fn format_emphasis(rope: &Rope, tree: &Tree, edits: &mut Vec<Edit>) {
+  let mut emphasis: Option<char> = None;
+  let mut strong: Option<char> = None;
+  for node in node_util::find_nested_nodes(tree, &[181 /* emphasis */, 182 /* strong emphasis */]) {
+    let (expected, length) = match node.kind_id() {
+      181 => (&mut emphasis, 1),
+      _other => (&mut strong, 2),
+    };
+    let delimiter = match node_text(rope, &node).chars().next() {
+      Some(delimiter) if matches!(delimiter, '*' | '_') => delimiter,
+      _other => continue,
+    };
+    let expected = *expected.get_or_insert(delimiter);
+    let (start, end) = (node.start_byte(), node.end_byte());
+    // underscores don't delimit emphasis within a word, like in "foo*bar*baz", which the
+    // `EMPHASIS_STYLE` rule skips as well
+    if expected == '_' && is_within_word(rope, start, end) {
+      continue;
+    }
+    if delimiter != expected && end - start > length * 2 {
+      let text = expected.to_string().repeat(length);
+      edits.push(Edit::new(start, start + length, text.clone()));
+      edits.push(Edit::new(end - length, end, text));
+    }
+  }
+}
+
+fn format_fences(rope: &Rope, tree: &Tree, edits: &mut Vec<Edit>) {
+  let document_end = trimmed_end(rope, 0, rope.len());
+  for node in node_util::find_nested_nodes(tree, &[136 /* fenced code block */]) {
+    let text = node_text(rope, &node);
+    let length = text.chars().take_while(|c| *c == '~').count();
+    // tildes can only be replaced when the code block doesn't contain backticks itself
+    if length < 3 || text.contains('`') {
+      continue;
+    }
+
+    // the closing fence follows the same container prefixes, like "> ", as the opening fence
+    let last_line = text.rsplit('\n').next().unwrap_or_default();
+    let column = node.start_position().column;
+    let closing_line = last_line
+      .get(column.min(last_line.len())..)
+      .unwrap_or_default();
+    let closing = closing_line.trim();
+    let is_closed = node.end_position().row > node.start_position().row
+      && closing.len() >= length
+      && closing.chars().all(|c| c == '~');
+    // an opening fence without a closing fence may only be replaced when it runs to the end
+    if !is_closed && node.end_byte() < document_end {
+      continue;
+    }
+
+    let start = node.start_byte();
+    edits.push(Edit::new(start, start + length, "`".repeat(length)));
+    if is_closed {
+      let closing_start =
+        node.end_byte() - closing_line.len() + closing_line.find('~').unwrap_or(0);
+      edits.push(Edit::new(
+        closing_start,
+        closing_start + closing.len(),
+        "`".repeat(closing.len()),
+      ));
+    }
+  }
+}
+
+fn wrap_paragraphs(rope: &Rope, tree: &Tree, width: usize, edits: &mut Vec<Edit>) {
+  for paragraph in node_util::find_nested_nodes(tree, &[124 /* paragraph */]) {
+    // only paragraphs at the top level or in list items are wrapped, which don't have prefixes
+    let parent = paragraph.parent().map(|parent| parent.kind_id());
+    if !matches!(
+      parent,
+      Some(119 /* document */) | Some(160 /* list item */) | Some(161 /* task list item */)
+    ) {
+      continue;
+    }
+    // hard line breaks have to stay where they are
+    if node_util::find_child(&paragraph, 110 /* hard line break */).is_some() {
+      continue;
+    }
+
+    let indent = " ".repeat(paragraph.start_position().column);
+    let words = paragraph_words(rope, &paragraph);
+    let mut column = paragraph.start_position().column;
+    for (index, (start, end)) in words.iter().enumerate() {
+      let word = rope.slice_to_cow(*start..*end);
+      let word_width = UnicodeWidthStr::width(word.as_ref());
+      if index == 0 {
+        column += word_width;
+        continue;
+      }
+
+      let previous_end = words[index - 1].1;
+      let gap = if column + 1 + word_width > width && can_start_line(&word) {
+        column = indent.len() + word_width;
+        format!("\n{}", indent)
+      } else {
+        column += 1 + word_width;
+        " ".to_string()
+      };
+      edits.extend(Edit::minimal(rope, previous_end, *start, gap));
+    }
+  }
+}
+
+fn paragraph_words(rope: &Rope, paragraph: &Node) -> Vec<(usize, usize)> {
+  // whitespace in code spans, inline html, and task list markers doesn't separate words
+  let protected = node_util::find_descendants(
+    paragraph,
+    &[
+      200, /* code span */
+      201, /* html open tag */
+      204, /* html close tag */
+      236, /* task list item marker */
+    ],
+  )
+  .into_iter()
+  .map(|node| (node.start_byte(), node.end_byte()))
+  .collect::<Vec<(usize, usize)>>();
+
+  let text = node_text(rope, paragraph);
+  let offset = paragraph.start_byte();
+  let mut words: Vec<(usize, usize)> = Vec::new();
+  let mut word_start: Option<usize> = None;
+  for (index, c) in text.char_indices() {
+    let position = offset + index;
+    let is_protected = protected
+      .iter()
+      .any(|(start, end)| *start < position && position < *end);
+    if c.is_whitespace() && !is_protected {
+      if let Some(start) = word_start.take() {
+        words.push((start, position));
+      }
+    } else if word_start.is_none() {
+      word_start = Some(position);
+    }
+  }
+  if let Some(start) = word_start {
+    words.push((start, offset + text.len()));
+  }
+  words
+}
+
+pub fn is_within_word(rope: &Rope, start: usize, end: usize) -> bool {
+  let before = rope
+    .prev_codepoint_offset(start)
+    .map(|offset| rope.slice_to_cow(offset..start));
+  let after = rope
+    .next_codepoint_offset(end)
+    .map(|offset| rope.slice_to_cow(end..offset));
+  before
+    .into_iter()
+    .chain(after)
+    .any(|c| c.chars().any(|c| c.is_alphanumeric()))
+}
+
+fn can_start_line(word: &str) -> bool {
+  // words that would start a block, like a list or a heading, can't be moved to a new line
+  let first = match word.chars().next() {
+    Some(first) => first,
+    None => return false,
+  };
+  let is_marker_run = |markers: &[char]| word.chars().all(|c| markers.contains(&c));
+  let is_ordered_marker = word.len() > 1
+    && word[..word.len() - 1].chars().all(|c| c.is_ascii_digit())
+    && (word.ends_with('.') || word.ends_with(')'));
+  !(matches!(first, '>' | '<' | '|')
+    || is_marker_run(&['#'])
+    || is_marker_run(&['-'])
+    || is_marker_run(&['*'])
+    || is_marker_run(&['_'])
+    || is_marker_run(&['='])
+    || word == "+"
+    || word.starts_with("```")
+    || word.starts_with("~~~")
+    || is_ordered_marker)
+}
+
+fn node_text(rope: &Rope, node: &Node) -> String {
+  rope
+    .slice_to_cow(node.start_byte()..node.end_byte())
+    .into_owned()
+}
+
+fn trimmed_range(rope: &Rope, node: &Node) -> (usize, usize) {
+  let text = rope.slice_to_cow(node.start_byte()..node.end_byte());
+  let start = node.start_byte() + (text.len() - text.trim_start().len());
+  (start, trimmed_end(rope, start, node.end_byte()))
+}
+
+fn trimmed_end(rope: &Rope, start: usize, end: usize) -> usize {
+  let text = rope.slice_to_cow(start..end);
+  start + text.trim_end().len()
+}
+
+fn common_prefix(a: &str, b: &str) -> usize {
+  a.char_indices()
+    .zip(b.chars())
+    .find(|((_, a), b)| a != b)
+    .map(|((index, _), _)| index)
+    .unwrap_or_else(|| a.len().min(b.len()))
+}
+
+fn common_suffix(a: &str, b: &str) -> usize {
+  a.chars()
+    .rev()
+    .zip(b.chars().rev())
+    .take_while(|(a, b)| a == b)
+    .map(|(a, _)| a.len_utf8())
+    .sum()
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+  use crate::parser::parser;
+
+  pub fn apply_edits(text: &str, edits: Vec<Edit>) -> String {
+    // edits are applied back to front, so that their offsets stay valid
+    let mut output = text.to_string();
+    for edit in edits.into_iter().rev() {
+      output.replace_range(edit.start..edit.end, &edit.text);
+    }
+    output
+  }
+
+  pub fn format_text(text: &str, wrap: Option<usize>) -> String {
+    let tree = parser().parse(text, None).unwrap();
+    apply_edits(text, format(&Rope::from(text), &tree, wrap))
+  }
+
+  #[test]
+  fn normalizes_headings() {
+    let text = "#  Title ##\n\nSub\n---\n";
+    assert_eq!(format_text(text, None), "# Title\n\n## Sub\n");
+  }
+
+  #[test]
+  fn wraps_paragraphs() {
+    let text = "one two three four\nalpha beta - gamma\n\n- seven eight nine\n";
+    assert_eq!(
+      format_text(text, Some(10)),
+      "one two\nthree four\nalpha beta -\ngamma\n\n- seven\n  eight\n  nine\n"
+    );
+  }
+
+  #[test]
+  fn normalizes_list_markers() {
+    let text = "- a\n- b\n\npara\n\n* c\n* d\n\n1. e\n7. f\n";
+    assert_eq!(
+      format_text(text, None),
+      "- a\n- b\n\npara\n\n- c\n- d\n\n1. e\n2. f\n"
+    );
+  }
+
+  #[test]
+  fn keeps_adjacent_lists_apart() {
+    let text = "- a\n- b\n\n* c\n* d\n";
+    assert_eq!(format_text(text, None), text);
+    let text = "- a\n\npara\n\n* b\n- c\n";
+    assert_eq!(format_text(text, None), "- a\n\npara\n\n- b\n* c\n");
+  }
+
+  #[test]
+  fn normalizes_emphasis_delimiters() {
+    let text = "*a* and _b_, **c** and __d__\n";
+    assert_eq!(format_text(text, None), "*a* and *b*, **c** and **d**\n");
+  }
+
+  #[test]
+  fn keeps_intraword_emphasis() {
+    let text = "_a_ then foo*bar*baz and *c*\n";
+    assert_eq!(format_text(text, None), "_a_ then foo*bar*baz and _c_\n");
+  }
+
+  #[test]
+  fn replaces_tilde_fences() {
+    let text = "~~~py\nx\n~~~\n";
+    assert_eq!(format_text(text, None), "```py\nx\n```\n");
+  }
+
+  #[test]
+  fn replaces_fences_in_containers() {
+    let text = "> ~~~\n> code\n> ~~~\n\nafter\n\n# H\n";
+    assert_eq!(
+      format_text(text, None),
+      "> ```\n> code\n> ```\n\nafter\n\n# H\n"
+    );
+    let text = "- item\n\n  ~~~\n  code\n  ~~~\n";
+    assert_eq!(format_text(text, None), "- item\n\n  ```\n  code\n  ```\n");
+  }
+
+  #[test]
+  fn keeps_unclosed_fences_before_other_blocks() {
+    let text = "> ~~~\n> unclosed\n\nafter\n";
+    assert_eq!(format_text(text, None), text);
+    assert_eq!(format_text("~~~\nunclosed\n", None), "```\nunclosed\n");
+  }
+
+  #[test]
+  fn keeps_fences_containing_backticks() {
+    let text = "~~~\n```\n~~~\n";
+    assert_eq!(format_text(text, None), text);
+  }
+}
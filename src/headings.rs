@@ -0,0 +1,77 @@
+use crate::{document::offset_to_position, node_util};
+
+use lspower::lsp::Range;
+use std::collections::HashMap;
+use tree_sitter::{Node, Tree};
+use xi_rope::Rope;
+
+#[derive(Debug, Clone)]
+pub struct Heading {
+  pub text: String,
+  pub slug: String,
+  pub range: Range,
+  pub content_start: usize,
+  pub content_end: usize,
+}
+
+pub fn find_headings(rope: &Rope, tree: &Tree) -> Vec<Heading> {
+  let front_matter = node_util::find_front_matter(tree);
+  let mut slugs: HashMap<String, usize> = HashMap::new();
+
+  node_util::find_nodes(tree, &[129 /* setext heading */, 132 /* atx heading */])
+    .into_iter()
+    .filter(|node| Some(*node) != front_matter)
+    .filter_map(|node| {
+      let content = node_util::heading_content(&node)?;
+      let mut text = String::new();
+      collect_text(rope, &content, &mut text);
+      let text = text.trim().to_string();
+
+      // duplicate slugs are numbered in the order that they appear in, like on GitHub
+      let slug = slugify(&text);
+      let count = slugs.entry(slug.clone()).or_insert(0);
+      let slug = match *count {
+        0 => slug,
+        count => format!("{}-{}", slug, count),
+      };
+      *count += 1;
+
+      // the content's range excludes the whitespace around it, which is part of the atx node
+      let source = rope.slice_to_cow(content.start_byte()..content.end_byte());
+      let content_start = content.start_byte() + (source.len() - source.trim_start().len());
+      let content_end = content.start_byte() + source.trim_end().len();
+      Some(Heading {
+        text,
+        slug,
+        range: Range::new(
+          offset_to_position(rope, node.start_byte()),
+          offset_to_position(rope, node.end_byte()),
+        ),
+        content_start,
+        content_end: content_end.max(content_start),
+      })
+    })
+    .collect()
+}
+
+pub fn slugify(text: &str) -> String {
+  // this follows GitHub's anchors, which drop punctuation and replace spaces with hyphens
+  text
+    .trim()
+    .to_lowercase()
+    .chars()
+    .filter(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+    .map(|c| if c == ' ' { '-' } else { c })
+    .collect()
+}
+
+fn collect_text(rope: &Rope, node: &Node, text: &mut String) {
+  let mut cursor = node.walk();
+  for child in node.children(&mut cursor) {
+    match child.kind_id() {
+      211 /* text */ => text.push_str(&rope.slice_to_cow(child.start_byte()..child.end_byte())),
+      190 /* link destination */ | 191 /* link title */ => (),
+      _other => collect_text(rope, &child, text),
+    }
+  }
+}
@@ -0,0 +1,79 @@
+use crate::{rules::BuiltinRule, validator::TextSuggestion};
+
+use nlprule::rule::{Example, Rule};
+
+// only a few examples are shown, since some rules come with dozens of them
+const MAX_EXAMPLES: usize = 3;
+
+pub fn rule_hover(rule: &Rule, suggestion: &TextSuggestion) -> String {
+  let mut sections = vec![format!("**{}**", rule.name())];
+
+  let mut category = format!(
+    "`{}` · {} (`{}`)",
+    rule.id(),
+    rule.category_name(),
+    rule.category_id()
+  );
+  if let Some(category_type) = rule.category_type() {
+    category.push_str(&format!(" · {}", category_type));
+  }
+  sections.push(category);
+  sections.extend(alternatives(suggestion));
+
+  let examples = rule
+    .examples()
+    .iter()
+    .take(MAX_EXAMPLES)
+    .map(|example| format!("- {}", example_text(example)))
+    .collect::<Vec<String>>();
+  if !examples.is_empty() {
+    sections.push(format!("Examples:\n{}", examples.join("\n")));
+  }
+
+  if let Some(url) = rule.url() {
+    sections.push(format!("[More information]({})", url));
+  }
+  sections.join("\n\n")
+}
+
+pub fn builtin_rule_hover(rule: &BuiltinRule, suggestion: &TextSuggestion) -> String {
+  let mut sections = vec![
+    format!("**{}**", rule.description),
+    format!("`{}` · {}", rule.id, rule.category),
+  ];
+  sections.extend(alternatives(suggestion));
+  sections.join("\n\n")
+}
+
+fn alternatives(suggestion: &TextSuggestion) -> Option<String> {
+  if suggestion.alternatives.is_empty() {
+    return None;
+  }
+
+  let alternatives = suggestion
+    .alternatives
+    .iter()
+    .map(|alternative| match alternative.text.as_ref() {
+      "" => "- Remove text".to_string(),
+      text => format!("- `{}`", text),
+    })
+    .collect::<Vec<String>>();
+  Some(format!("Replace with:\n{}", alternatives.join("\n")))
+}
+
+fn example_text(example: &Example) -> String {
+  let suggestion = match example.suggestion() {
+    Some(suggestion) => suggestion,
+    None => return format!("Correct: {}", example.text()),
+  };
+
+  // examples' suggestions use character indices, like every other suggestion from nlprule
+  let replacement = suggestion.replacements.first().cloned().unwrap_or_default();
+  let chars = example.text().chars().collect::<Vec<char>>();
+  let start = suggestion.start.min(chars.len());
+  let end = suggestion.end.clamp(start, chars.len());
+  let mut corrected = chars[..start].iter().collect::<String>();
+  corrected.push_str(&replacement);
+  corrected.extend(&chars[end..]);
+  format!("Incorrect: {} → {}", example.text(), corrected)
+}
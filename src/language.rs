@@ -0,0 +1,77 @@
+use std::path::Path;
+use whatlang::Lang;
+
+pub const LANGUAGES: &[&str] = &["en", "de", "es"];
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+pub fn normalize(language: &str) -> Option<&'static str> {
+  // regional variants, like "en-GB" or "de_AT", share the same rules
+  let code = language
+    .trim()
+    .split(&['-', '_'][..])
+    .next()?
+    .to_lowercase();
+  LANGUAGES.iter().copied().find(|language| *language == code)
+}
+
+pub fn from_front_matter(text: &str) -> Option<&'static str> {
+  let mut lines = text.lines();
+  if lines.next()?.trim_end() != "---" {
+    return None;
+  }
+
+  lines
+    .take_while(|line| !matches!(line.trim_end(), "---" | "..."))
+    .filter_map(|line| {
+      let (key, value) = line.split_once(':')?;
+      match key.trim() {
+        "lang" | "language" => Some(value.trim().trim_matches(|c| c == '"' || c == '\'')),
+        _other => None,
+      }
+    })
+    .find_map(normalize)
+}
+
+pub fn from_path(path: &Path) -> Option<&'static str> {
+  // files may be suffixed with their language, e.g. "README.de.md"
+  let stem = Path::new(path.file_stem()?);
+  normalize(stem.extension()?.to_str()?)
+}
+
+pub fn from_binary_path(path: &Path) -> &'static str {
+  // nlprule's binaries are prefixed with their language, e.g. "de_rules.bin.xz"
+  path
+    .file_name()
+    .and_then(|name| name.to_str())
+    .and_then(|name| name.split('_').next())
+    .and_then(normalize)
+    .unwrap_or(DEFAULT_LANGUAGE)
+}
+
+pub fn detect(text: &str) -> Option<Lang> {
+  // short texts, like headings, are too ambiguous and stay in the document's language
+  if text.split_whitespace().count() < 5 {
+    return None;
+  }
+
+  whatlang::detect(text)
+    .filter(|info| info.is_reliable())
+    .map(|info| info.lang())
+}
+
+pub fn to_lang(language: &str) -> Lang {
+  match language {
+    "de" => Lang::Deu,
+    "es" => Lang::Spa,
+    _other => Lang::Eng,
+  }
+}
+
+pub fn from_lang(lang: Lang) -> Option<&'static str> {
+  match lang {
+    Lang::Eng => Some("en"),
+    Lang::Deu => Some("de"),
+    Lang::Spa => Some("es"),
+    _other => None,
+  }
+}
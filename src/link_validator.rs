@@ -0,0 +1,84 @@
+use crate::{
+  config::Config,
+  directives::Directives,
+  links::{self, Link, LinkKind},
+  rules::{BuiltinRule, MISSING_ANCHOR, MISSING_FILE, MISSING_IMAGE},
+  workspace::{self, is_markdown_file, Workspace},
+};
+
+use lspower::lsp::Diagnostic;
+use std::path::Path;
+use tree_sitter::Tree;
+use url::Url;
+use xi_rope::Rope;
+
+pub struct LinkValidator<'a> {
+  workspace: &'a mut Workspace,
+  uri: &'a Url,
+  root: Option<&'a Path>,
+}
+
+impl<'a> LinkValidator<'a> {
+  pub fn new(workspace: &'a mut Workspace, uri: &'a Url, root: Option<&'a Path>) -> Self {
+    Self {
+      workspace,
+      uri,
+      root,
+    }
+  }
+
+  pub fn validate(
+    &mut self,
+    rope: &Rope,
+    tree: &Tree,
+    config: &Config,
+    directives: &Directives,
+  ) -> Vec<Diagnostic> {
+    links::find_links(rope, tree)
+      .into_iter()
+      .filter_map(|link| {
+        let (rule, message) = self.check(&link)?;
+        rule.diagnostic(
+          rope,
+          config,
+          directives,
+          message,
+          link.destination_start,
+          link.destination_end,
+        )
+      })
+      .collect()
+  }
+
+  fn check(&mut self, link: &Link) -> Option<(&'static BuiltinRule, String)> {
+    // only local files are checked, since external urls would need network requests
+    if matches!(link.kind, LinkKind::Autolink | LinkKind::EmailAutolink) {
+      return None;
+    }
+    let target = workspace::resolve_link(self.uri, self.root, &link.destination)?;
+    let path = target.uri.to_file_path().ok()?;
+    let indexed = self.workspace.headings(&path).is_some();
+    let file = link.destination.split('#').next().unwrap_or_default();
+    if !indexed && !path.exists() {
+      return if link.kind == LinkKind::Image {
+        Some((&MISSING_IMAGE, format!("Image {} doesn't exist", file)))
+      } else {
+        Some((&MISSING_FILE, format!("File {} doesn't exist", file)))
+      };
+    }
+
+    let fragment = target.fragment?;
+    if link.kind == LinkKind::Image || !is_markdown_file(&path) {
+      return None;
+    }
+    let headings = self.workspace.headings(&path)?;
+    let message = if headings.iter().any(|heading| heading.slug == fragment) {
+      return None;
+    } else if file.is_empty() {
+      format!("Heading #{} doesn't exist in this document", fragment)
+    } else {
+      format!("Heading #{} doesn't exist in {}", fragment, file)
+    };
+    Some((&MISSING_ANCHOR, message))
+  }
+}
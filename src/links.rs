@@ -0,0 +1,385 @@
+use crate::node_util;
+
+use tree_sitter::{Node, Tree};
+use xi_rope::Rope;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LabelKind {
+  Link,
+  Footnote,
+}
+
+pub struct Label {
+  pub kind: LabelKind,
+  pub name: String,
+  pub start: usize,
+  pub end: usize,
+}
+
+impl Label {
+  pub fn matches(&self, other: &Label) -> bool {
+    self.kind == other.kind && self.name == other.name
+  }
+
+  pub fn contains(&self, offset: usize) -> bool {
+    self.start <= offset && offset <= self.end
+  }
+
+  pub fn name_range(&self) -> (usize, usize) {
+    // footnote labels include their caret, which isn't part of the name
+    match self.kind {
+      LabelKind::Link => (self.start, self.end),
+      LabelKind::Footnote => (self.start + 1, self.end),
+    }
+  }
+
+  pub fn display(&self) -> String {
+    match self.kind {
+      LabelKind::Link => format!("[{}]", self.name),
+      LabelKind::Footnote => format!("[^{}]", self.name),
+    }
+  }
+}
+
+pub struct Reference {
+  pub label: Label,
+  pub start: usize,
+  pub end: usize,
+  // shortcut references, like "[label]", are only links when their label is defined
+  pub explicit: bool,
+}
+
+pub struct Definition {
+  pub label: Label,
+  pub start: usize,
+  pub end: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkKind {
+  Link,
+  Image,
+  Definition,
+  Autolink,
+  EmailAutolink,
+}
+
+pub struct Link {
+  pub kind: LinkKind,
+  pub destination: String,
+  pub start: usize,
+  pub end: usize,
+  pub destination_start: usize,
+  pub destination_end: usize,
+}
+
+impl Link {
+  pub fn contains(&self, offset: usize) -> bool {
+    self.start <= offset && offset <= self.end
+  }
+}
+
+pub fn find_links(rope: &Rope, tree: &Tree) -> Vec<Link> {
+  node_util::find_nested_nodes(
+    tree,
+    &[
+      126, /* link reference definition */
+      184, /* link */
+      185, /* image */
+      192, /* www autolink */
+      194, /* uri autolink */
+      196, /* email autolink */
+    ],
+  )
+  .into_iter()
+  .filter(|node| !is_footnote_definition_node(rope, node))
+  .filter_map(|node| {
+    let kind = match node.kind_id() {
+      126 => LinkKind::Definition,
+      185 => LinkKind::Image,
+      192 | 194 => LinkKind::Autolink,
+      196 => LinkKind::EmailAutolink,
+      _other => LinkKind::Link,
+    };
+    // autolinks are their own destination, while other links have a destination node
+    let destination = match kind {
+      LinkKind::Autolink | LinkKind::EmailAutolink => node,
+      _other => node_util::find_child(&node, 190 /* link destination */)?,
+    };
+    let (mut start, mut end) = (destination.start_byte(), destination.end_byte());
+    // destinations may be wrapped in angle brackets, e.g. "<./some file.md>"
+    let text = rope.slice_to_cow(start..end);
+    if text.starts_with('<') && text.ends_with('>') && text.len() >= 2 {
+      start += 1;
+      end -= 1;
+    }
+    Some(Link {
+      kind,
+      destination: rope.slice_to_cow(start..end).into_owned(),
+      start: node.start_byte(),
+      end: node.end_byte(),
+      destination_start: start,
+      destination_end: end,
+    })
+  })
+  .collect()
+}
+
+pub struct References {
+  pub references: Vec<Reference>,
+  pub definitions: Vec<Definition>,
+}
+
+impl References {
+  pub fn parse(rope: &Rope, tree: &Tree) -> Self {
+    let mut references: Vec<Reference> = Vec::new();
+    let mut definitions: Vec<Definition> = Vec::new();
+
+    let nodes = node_util::find_nested_nodes(
+      tree,
+      &[
+        126, /* link reference definition */
+        184, /* link */
+        185, /* image */
+      ],
+    );
+    for node in nodes {
+      let link_label = node_util::find_child(&node, 233 /* link label */);
+      if node.kind_id() == 126 {
+        // footnotes with a single word, like "[^note]: Text.", are parsed as link definitions
+        if let Some(label) = link_label.and_then(|label| parse_label(rope, &label, true)) {
+          definitions.push(Definition {
+            label,
+            start: node.start_byte(),
+            end: node.end_byte(),
+          });
+        }
+        continue;
+      } else if node_util::find_child(&node, 190 /* link destination */).is_some() {
+        continue;
+      }
+
+      let is_link = node.kind_id() == 184;
+      if let Some(label) = link_label.and_then(|label| parse_label(rope, &label, false)) {
+        references.push(Reference {
+          label,
+          start: node.start_byte(),
+          end: node.end_byte(),
+          explicit: true,
+        });
+        continue;
+      }
+
+      let text = match node_util::find_child(&node, 234 /* link text */)
+        .or_else(|| node_util::find_child(&node, 230 /* image description */))
+      {
+        Some(text) => text,
+        None => continue,
+      };
+      let label = match parse_label(rope, &text, is_link) {
+        Some(label) => label,
+        None => continue,
+      };
+
+      if label.kind == LabelKind::Footnote && is_footnote_definition(rope, &node) {
+        // consecutive definitions share a paragraph, so each one ends with its own line
+        let mut end = node.parent().unwrap_or(node).end_byte();
+        let mut sibling = node.next_sibling();
+        while let Some(next) = sibling {
+          if next.kind_id() == 111 {
+            end = next.start_byte();
+            break;
+          }
+          sibling = next.next_sibling();
+        }
+        definitions.push(Definition {
+          label,
+          start: node.start_byte(),
+          end,
+        });
+      } else {
+        let source = rope.slice_to_cow(node.start_byte()..node.end_byte());
+        references.push(Reference {
+          explicit: label.kind == LabelKind::Footnote || source.ends_with("[]"),
+          label,
+          start: node.start_byte(),
+          end: node.end_byte(),
+        });
+      }
+    }
+
+    Self {
+      references,
+      definitions,
+    }
+  }
+
+  pub fn definition(&self, label: &Label) -> Option<&Definition> {
+    // the first definition of a label takes precedence
+    self
+      .definitions
+      .iter()
+      .find(|definition| definition.label.matches(label))
+  }
+
+  pub fn references_to<'a>(&'a self, label: &'a Label) -> impl Iterator<Item = &'a Reference> {
+    self
+      .references
+      .iter()
+      .filter(move |reference| reference.label.matches(label))
+  }
+
+  pub fn label_at(&self, offset: usize) -> Option<&Label> {
+    self
+      .references
+      .iter()
+      .find(|reference| reference.start <= offset && offset <= reference.end)
+      .map(|reference| &reference.label)
+      .or_else(|| {
+        self
+          .definitions
+          .iter()
+          .find(|definition| definition.label.contains(offset))
+          .map(|definition| &definition.label)
+      })
+  }
+}
+
+pub fn normalize_label(label: &str) -> String {
+  // labels match case-insensitively and regardless of their whitespace
+  label
+    .split_whitespace()
+    .collect::<Vec<&str>>()
+    .join(" ")
+    .to_lowercase()
+}
+
+fn parse_label(rope: &Rope, node: &Node, allow_footnote: bool) -> Option<Label> {
+  let text = rope.slice_to_cow(node.start_byte()..node.end_byte());
+  let (kind, name) = match text.strip_prefix('^') {
+    Some(name) if allow_footnote => (LabelKind::Footnote, name),
+    _other => (LabelKind::Link, text.as_ref()),
+  };
+  let name = normalize_label(name);
+  if name.is_empty() {
+    None
+  } else {
+    Some(Label {
+      kind,
+      name,
+      start: node.start_byte(),
+      end: node.end_byte(),
+    })
+  }
+}
+
+fn is_footnote_definition_node(rope: &Rope, node: &Node) -> bool {
+  node.kind_id() == 126
+    && node_util::find_child(node, 233 /* link label */)
+      .map(|label| {
+        rope
+          .slice_to_cow(label.start_byte()..label.end_byte())
+          .starts_with('^')
+      })
+      .unwrap_or(false)
+}
+
+fn is_footnote_definition(rope: &Rope, node: &Node) -> bool {
+  // footnote definitions are parsed as a paragraph line that starts with "[^label]:"
+  let at_line_start = match node.prev_sibling() {
+    Some(previous) => previous.kind_id() == 111, /* soft line break */
+    None => true,
+  };
+  at_line_start
+    && node.parent().map(|parent| parent.kind_id()) == Some(124 /* paragraph */)
+    && node.end_byte() < rope.len()
+    && rope.slice_to_cow(node.end_byte()..node.end_byte() + 1) == ":"
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::parser;
+
+  fn parse(text: &str) -> References {
+    let tree = parser().parse(text, None).unwrap();
+    References::parse(&Rope::from(text), &tree)
+  }
+
+  fn names(labels: Vec<&Label>) -> Vec<String> {
+    labels.into_iter().map(|label| label.display()).collect()
+  }
+
+  #[test]
+  fn finds_reference_definitions() {
+    let references = parse("[text][label] and [label]\n\n[label]: ./file.md\n");
+    assert_eq!(
+      names(references.definitions.iter().map(|d| &d.label).collect()),
+      ["[label]"]
+    );
+    assert_eq!(
+      names(references.references.iter().map(|r| &r.label).collect()),
+      ["[label]", "[label]"]
+    );
+    assert!(references.references[0].explicit);
+    assert!(!references.references[1].explicit);
+  }
+
+  #[test]
+  fn finds_single_word_footnote_definitions() {
+    let references = parse("A note[^n].\n\n[^n]: Note.\n");
+    assert_eq!(
+      names(references.definitions.iter().map(|d| &d.label).collect()),
+      ["[^n]"]
+    );
+    assert_eq!(
+      names(references.references.iter().map(|r| &r.label).collect()),
+      ["[^n]"]
+    );
+  }
+
+  #[test]
+  fn finds_consecutive_footnote_definitions() {
+    let text = "[^a]: First one.\n[^b]: Second one.\n\nUse [^a] and [^b].\n";
+    let references = parse(text);
+    let definitions = &references.definitions;
+    assert_eq!(
+      names(definitions.iter().map(|d| &d.label).collect()),
+      ["[^a]", "[^b]"]
+    );
+    assert_eq!(
+      &text[definitions[0].start..definitions[0].end],
+      "[^a]: First one."
+    );
+    assert_eq!(
+      &text[definitions[1].start..definitions[1].end],
+      "[^b]: Second one."
+    );
+    assert_eq!(
+      names(references.references.iter().map(|r| &r.label).collect()),
+      ["[^a]", "[^b]"]
+    );
+  }
+
+  #[test]
+  fn finds_www_autolinks() {
+    let text = "Visit www.example.com/path. Or <https://example.org>.\n";
+    let tree = parser().parse(text, None).unwrap();
+    let links = find_links(&Rope::from(text), &tree);
+    assert_eq!(
+      links
+        .iter()
+        .map(|link| (link.kind, link.destination.as_str()))
+        .collect::<Vec<_>>(),
+      [
+        (LinkKind::Autolink, "www.example.com/path"),
+        (LinkKind::Autolink, "https://example.org"),
+      ]
+    );
+  }
+
+  #[test]
+  fn normalizes_labels() {
+    assert_eq!(normalize_label("  Some\n  Label "), "some label");
+  }
+}
@@ -0,0 +1,320 @@
+use crate::{
+  config::Config,
+  directives::Directives,
+  format, node_util,
+  rules::{
+    problem_diagnostics, Problem, BLANK_LINES_AROUND_FENCES, EMPHASIS_STYLE, HARD_TABS,
+    HEADING_INCREMENT, LIST_MARKER_STYLE, SINGLE_H1, TRAILING_WHITESPACE,
+  },
+};
+
+use lspower::lsp::Diagnostic;
+use std::{borrow::Cow, ops::Range};
+use tree_sitter::{Node, Tree};
+use xi_rope::{LinesMetric, Rope};
+
+pub fn lint(rope: &Rope, tree: &Tree, config: &Config, directives: &Directives) -> Vec<Diagnostic> {
+  problem_diagnostics(rope, config, directives, find_problems(rope, tree))
+}
+
+fn find_problems(rope: &Rope, tree: &Tree) -> Vec<Problem> {
+  let mut problems: Vec<Problem> = Vec::new();
+  check_headings(tree, &mut problems);
+  check_list_markers(rope, tree, &mut problems);
+  check_emphasis(rope, tree, &mut problems);
+  check_lines(rope, tree, &mut problems);
+  check_fences(rope, tree, &mut problems);
+  problems
+}
+
+fn check_headings(tree: &Tree, problems: &mut Vec<Problem>) {
+  let front_matter = node_util::find_front_matter(tree);
+  let mut previous_level: Option<usize> = None;
+  let mut has_h1 = false;
+
+  let headings = node_util::find_nodes(tree, &[129 /* setext heading */, 132 /* atx heading */]);
+  for node in headings
+    .into_iter()
+    .filter(|node| Some(*node) != front_matter)
+  {
+    let level = match node_util::heading_level(&node) {
+      Some(level) => level,
+      None => continue,
+    };
+
+    if let Some(previous_level) = previous_level {
+      if level > previous_level + 1 {
+        problems.push(Problem {
+          rule: &HEADING_INCREMENT,
+          message: format!(
+            "Expected a level {} heading after a level {} heading, but found level {}",
+            previous_level + 1,
+            previous_level,
+            level
+          ),
+          start: node.start_byte(),
+          end: node.end_byte(),
+        });
+      }
+    }
+    previous_level = Some(level);
+
+    if level == 1 && has_h1 {
+      problems.push(Problem {
+        rule: &SINGLE_H1,
+        message: "Document has more than one top-level heading".to_string(),
+        start: node.start_byte(),
+        end: node.end_byte(),
+      });
+    }
+    has_h1 = has_h1 || level == 1;
+  }
+}
+
+fn check_list_markers(rope: &Rope, tree: &Tree, problems: &mut Vec<Problem>) {
+  // lists are expected to use the same markers that the formatter gives them
+  for (list, expected) in format::expected_bullets(rope, tree) {
+    let mut cursor = list.walk();
+    for item in list.children(&mut cursor) {
+      let node = match node_util::find_child(&item, 48 /* list marker */) {
+        Some(node) => node,
+        None => continue,
+      };
+      match node_text(rope, &node).chars().next() {
+        Some(marker) if matches!(marker, '-' | '*' | '+') && marker != expected => {
+          problems.push(Problem {
+            rule: &LIST_MARKER_STYLE,
+            message: format!(
+              "Expected bullet list marker \"{}\", but found \"{}\"",
+              expected, marker
+            ),
+            start: node.start_byte(),
+            end: node.start_byte() + 1,
+          })
+        }
+        _other => (),
+      }
+    }
+  }
+}
+
+fn check_emphasis(rope: &Rope, tree: &Tree, problems: &mut Vec<Problem>) {
+  let mut expected_emphasis: Option<char> = None;
+  let mut expected_strong: Option<char> = None;
+  for node in node_util::find_nested_nodes(tree, &[181 /* emphasis */, 182 /* strong emphasis */]) {
+    let (expected, kind) = match node.kind_id() {
+      181 => (&mut expected_emphasis, "emphasis"),
+      _other => (&mut expected_strong, "strong emphasis"),
+    };
+    let delimiter = match node_text(rope, &node).chars().next() {
+      Some(delimiter) if matches!(delimiter, '*' | '_') => delimiter,
+      _other => continue,
+    };
+    let expected = *expected.get_or_insert(delimiter);
+    let (start, end) = (node.start_byte(), node.end_byte());
+    // underscores don't delimit emphasis within a word, so it's kept as it is
+    if expected == '_' && format::is_within_word(rope, start, end) {
+      continue;
+    }
+    if delimiter != expected {
+      problems.push(Problem {
+        rule: &EMPHASIS_STYLE,
+        message: format!(
+          "Expected {} with \"{}\", but found \"{}\"",
+          kind, expected, delimiter
+        ),
+        start,
+        end,
+      });
+    }
+  }
+}
+
+fn check_lines(rope: &Rope, tree: &Tree, problems: &mut Vec<Problem>) {
+  // whitespace in code blocks may be significant, e.g. in makefiles or diffs
+  let code_blocks = node_util::find_nodes(
+    tree,
+    &[
+      134, /* indented code block */
+      136, /* fenced code block */
+    ],
+  )
+  .into_iter()
+  .map(|node| node.start_byte()..node.end_byte())
+  .collect::<Vec<Range<usize>>>();
+
+  let text = rope.slice_to_cow(..);
+  let mut offset = 0;
+  for line in text.split('\n') {
+    let start = offset;
+    offset += line.len() + 1;
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    if code_blocks.iter().any(|block| block.contains(&start)) {
+      continue;
+    }
+
+    let content = line.trim_end_matches(&[' ', '\t'][..]);
+    let whitespace = &line[content.len()..];
+    // two trailing spaces are a hard line break
+    if !whitespace.is_empty() && (content.is_empty() || whitespace != "  ") {
+      problems.push(Problem {
+        rule: &TRAILING_WHITESPACE,
+        message: "Line ends with trailing whitespace".to_string(),
+        start: start + content.len(),
+        end: start + line.len(),
+      });
+    }
+
+    let mut tabs = line.match_indices('\t').peekable();
+    while let Some((tab_start, _)) = tabs.next() {
+      let mut tab_end = tab_start + 1;
+      while tabs.peek().map(|(index, _)| *index) == Some(tab_end) {
+        tabs.next();
+        tab_end += 1;
+      }
+      problems.push(Problem {
+        rule: &HARD_TABS,
+        message: "Line contains hard tabs".to_string(),
+        start: start + tab_start,
+        end: start + tab_end,
+      });
+    }
+  }
+}
+
+fn check_fences(rope: &Rope, tree: &Tree, problems: &mut Vec<Problem>) {
+  let lines = rope.measure::<LinesMetric>() + 1;
+  for node in node_util::find_nested_nodes(tree, &[136 /* fenced code block */]) {
+    let start_line = node.start_position().row;
+    let end_line = node.end_position().row;
+
+    // fences that start on the same line as a list marker, like "- ```", don't need a blank line
+    let line_start = rope.offset_of_line(start_line);
+    let is_line_start = is_blank(&rope.slice_to_cow(line_start..node.start_byte()));
+    if start_line > 0 && is_line_start && !is_blank(&line_text(rope, start_line - 1)) {
+      let end = line_start + line_text(rope, start_line).len();
+      problems.push(Problem {
+        rule: &BLANK_LINES_AROUND_FENCES,
+        message: "Fenced code block should be preceded by a blank line".to_string(),
+        start: node.start_byte(),
+        end,
+      });
+    }
+
+    if end_line + 1 < lines && !is_blank(&line_text(rope, end_line + 1)) {
+      problems.push(Problem {
+        rule: &BLANK_LINES_AROUND_FENCES,
+        message: "Fenced code block should be followed by a blank line".to_string(),
+        start: rope.offset_of_line(end_line).max(node.start_byte()),
+        end: node.end_byte(),
+      });
+    }
+  }
+}
+
+fn node_text<'a>(rope: &'a Rope, node: &Node) -> Cow<'a, str> {
+  rope.slice_to_cow(node.start_byte()..node.end_byte())
+}
+
+fn line_text(rope: &Rope, line: usize) -> String {
+  let start = rope.offset_of_line(line);
+  let end = rope.offset_of_line(line + 1);
+  rope
+    .slice_to_cow(start..end)
+    .trim_end_matches(&['\r', '\n'][..])
+    .to_string()
+}
+
+fn is_blank(text: &str) -> bool {
+  // lines that only contain block quote markers are blank within their block quote
+  text.chars().all(|c| c.is_whitespace() || c == '>')
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{format::tests::format_text, parser::parser};
+
+  fn lint_text(text: &str) -> Vec<(&'static str, String)> {
+    let tree = parser().parse(text, None).unwrap();
+    find_problems(&Rope::from(text), &tree)
+      .into_iter()
+      .map(|problem| {
+        (
+          problem.rule.id,
+          text[problem.start..problem.end].to_string(),
+        )
+      })
+      .collect()
+  }
+
+  fn problem(id: &'static str, text: &str) -> (&'static str, String) {
+    (id, text.to_string())
+  }
+
+  #[test]
+  fn checks_heading_levels() {
+    assert_eq!(
+      lint_text("# One\n\n### Three\n\n## Two\n\n# Another\n"),
+      [
+        problem("HEADING_INCREMENT", "### Three"),
+        problem("SINGLE_H1", "# Another"),
+      ]
+    );
+    assert_eq!(lint_text("## Two\n\n### Three\n\n## Two\n"), []);
+  }
+
+  #[test]
+  fn checks_list_markers() {
+    assert_eq!(
+      lint_text("- a\n\npara\n\n* b\n\n1. c\n"),
+      [problem("LIST_MARKER_STYLE", "*")]
+    );
+    assert_eq!(lint_text("- a\n- b\n\n* c\n* d\n"), []);
+  }
+
+  #[test]
+  fn checks_emphasis() {
+    assert_eq!(
+      lint_text("*a* and _b_, __c__ and **d**\n"),
+      [
+        problem("EMPHASIS_STYLE", "_b_"),
+        problem("EMPHASIS_STYLE", "**d**"),
+      ]
+    );
+    assert_eq!(lint_text("_a_ and foo*bar*baz\n"), []);
+  }
+
+  #[test]
+  fn checks_whitespace() {
+    assert_eq!(
+      lint_text("trailing \nhard break  \n  \n\tindented\n\n```\nkept \t\n```\n"),
+      [
+        problem("TRAILING_WHITESPACE", " "),
+        problem("TRAILING_WHITESPACE", "  "),
+        problem("HARD_TABS", "\t"),
+      ]
+    );
+  }
+
+  #[test]
+  fn checks_blank_lines_around_fences() {
+    assert_eq!(
+      lint_text("text\n```\ncode\n```\ntext\n"),
+      [
+        problem("BLANK_LINES_AROUND_FENCES", "```"),
+        problem("BLANK_LINES_AROUND_FENCES", "```"),
+      ]
+    );
+    assert_eq!(
+      lint_text("text\n\n```\ncode\n```\n\n- ```\n  code\n  ```\n"),
+      []
+    );
+  }
+
+  #[test]
+  fn accepts_formatted_documents() {
+    let text = "- a\n- b\n\n* c\n\n_a_ and foo*bar*baz, __b__ and **c**\n";
+    assert_eq!(lint_text(&format_text(text, None)), []);
+  }
+}
@@ -1,12 +1,34 @@
 mod backend;
+mod cli;
+mod completion;
+mod config;
+mod dictionary;
+mod directives;
 mod document;
+mod fix;
+mod folding;
+mod format;
+mod headings;
+mod hover;
+mod language;
+mod link_validator;
+mod links;
+mod lint;
 mod node_util;
 mod parser;
+mod report;
+mod rules;
+mod stats;
+mod style;
+mod symbols;
+mod table;
 mod validator;
+mod workspace;
 
-use crate::backend::Backend;
+use crate::{backend::Backend, report::Format, validator::binaries_from_files};
 use async_std::{io::*, net};
 use lspower::{LspService, Server};
+use std::path::PathBuf;
 use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
@@ -20,6 +42,59 @@ struct Options {
 
   #[structopt(long)]
   socket: Option<u32>,
+
+  /// Loads nlprule rules from a .bin, .bin.gz, or .bin.xz file, e.g. "de_rules.bin.xz"
+  #[structopt(long = "rules", number_of_values = 1, parse(from_os_str))]
+  rules: Vec<PathBuf>,
+
+  /// Loads an nlprule tokenizer from a .bin, .bin.gz, or .bin.xz file, e.g. "de_tokenizer.bin.xz"
+  #[structopt(long = "tokenizer", number_of_values = 1, parse(from_os_str))]
+  tokenizers: Vec<PathBuf>,
+
+  #[structopt(subcommand)]
+  command: Option<Command>,
+}
+
+#[derive(StructOpt, Debug)]
+enum Command {
+  /// Checks markdown files and prints diagnostics
+  Check {
+    /// Files, directories, or glob patterns to check
+    #[structopt(required = true)]
+    paths: Vec<String>,
+
+    /// Output format for the reported issues
+    #[structopt(short, long, default_value = "human", possible_values = Format::VARIANTS)]
+    format: Format,
+  },
+  /// Applies automatic fixes to markdown files in place
+  Fix {
+    /// Files, directories, or glob patterns to fix
+    #[structopt(required = true)]
+    paths: Vec<String>,
+
+    /// Prints a unified diff of the fixes instead of writing them
+    #[structopt(long)]
+    dry_run: bool,
+
+    /// Only applies fixes for the given rule ids or names
+    #[structopt(long = "rule", number_of_values = 1)]
+    rules: Vec<String>,
+
+    /// Only applies fixes for the given categories
+    #[structopt(long = "category", number_of_values = 1)]
+    categories: Vec<String>,
+  },
+  /// Prints readability statistics for markdown files and each of their sections
+  Stats {
+    /// Files, directories, or glob patterns to measure
+    #[structopt(required = true)]
+    paths: Vec<String>,
+
+    /// Prints the statistics as JSON lines instead
+    #[structopt(long)]
+    json: bool,
+  },
 }
 
 #[async_std::main]
@@ -34,7 +109,31 @@ async fn main() -> Result<()> {
     return Ok(());
   }
 
-  let (service, messages) = LspService::new(Backend::new);
+  let binaries = binaries_from_files(&options.rules, &options.tokenizers);
+  if let Some(command) = options.command {
+    return match command {
+      Command::Check { paths, format } => {
+        if cli::check(&paths, format, &binaries)? {
+          std::process::exit(1);
+        }
+        Ok(())
+      }
+      Command::Fix {
+        paths,
+        dry_run,
+        rules,
+        categories,
+      } => cli::fix(
+        &paths,
+        &cli::FixFilter { rules, categories },
+        dry_run,
+        &binaries,
+      ),
+      Command::Stats { paths, json } => cli::stats(&paths, json, &binaries),
+    };
+  }
+
+  let (service, messages) = LspService::new(|client| Backend::new(client, binaries));
   if options.stdio {
     let input = stdin();
     let output = stdout();
@@ -34,6 +34,112 @@ pub fn find_text_nodes(tree: &Tree) -> Vec<Node> {
   nodes
 }
 
+pub fn find_nodes<'a>(tree: &'a Tree, kind_ids: &[u16]) -> Vec<Node<'a>> {
+  let mut cursor = tree.walk();
+  let mut nodes = Vec::new();
+  let mut recurse = true;
+
+  loop {
+    if (recurse && cursor.goto_first_child()) || cursor.goto_next_sibling() {
+      recurse = true;
+    } else if cursor.goto_parent() {
+      recurse = false;
+    } else {
+      break;
+    }
+
+    let node = cursor.node();
+    if kind_ids.contains(&node.kind_id()) {
+      nodes.push(node);
+      recurse = false;
+    }
+  }
+
+  nodes
+}
+
+pub fn find_nested_nodes<'a>(tree: &'a Tree, kind_ids: &[u16]) -> Vec<Node<'a>> {
+  let mut cursor = tree.walk();
+  let mut nodes = Vec::new();
+  let mut recurse = true;
+
+  loop {
+    if (recurse && cursor.goto_first_child()) || cursor.goto_next_sibling() {
+      recurse = true;
+    } else if cursor.goto_parent() {
+      recurse = false;
+    } else {
+      break;
+    }
+
+    let node = cursor.node();
+    if recurse && kind_ids.contains(&node.kind_id()) {
+      nodes.push(node);
+    }
+  }
+
+  nodes
+}
+
+pub fn find_descendants<'a>(node: &Node<'a>, kind_ids: &[u16]) -> Vec<Node<'a>> {
+  let mut nodes = Vec::new();
+  let mut cursor = node.walk();
+  for child in node.children(&mut cursor) {
+    if kind_ids.contains(&child.kind_id()) {
+      nodes.push(child);
+    } else {
+      nodes.extend(find_descendants(&child, kind_ids));
+    }
+  }
+  nodes
+}
+
+pub fn find_front_matter(tree: &Tree) -> Option<Node<'_>> {
+  // front matter is parsed as a thematic break that's followed by a setext heading
+  let root = tree.root_node();
+  let start = root.child(0)?;
+  let heading = root.child(1)?;
+  if start.kind_id() == 122 /* thematic break */ && start.start_byte() == 0 && heading.kind_id() == 129
+  /* setext heading */
+  {
+    Some(heading)
+  } else {
+    None
+  }
+}
+
+pub fn heading_level(node: &Node) -> Option<usize> {
+  let mut cursor = node.walk();
+  let level = match node.kind_id() {
+    132 /* atx heading */ => node
+      .children(&mut cursor)
+      .map(|child| child.kind_id())
+      .find(|kind_id| (11..=16).contains(kind_id))
+      .map(|kind_id| kind_id as usize - 10),
+    129 /* setext heading */ => node
+      .children(&mut cursor)
+      .find_map(|child| match child.kind_id() {
+        8 /* setext h1 underline */ => Some(1),
+        9 /* setext h2 underline */ => Some(2),
+        _other => None,
+      }),
+    _other => None,
+  };
+  level
+}
+
+pub fn heading_content<'a>(node: &Node<'a>) -> Option<Node<'a>> {
+  find_child(node, 169 /* heading content */)
+}
+
+pub fn find_child<'a>(node: &Node<'a>, kind_id: u16) -> Option<Node<'a>> {
+  let mut cursor = node.walk();
+  let child = node
+    .children(&mut cursor)
+    .find(|child| child.kind_id() == kind_id);
+  child
+}
+
 pub struct TextChunk {
   pub clean_length: usize,
   pub start: usize,
@@ -45,6 +151,28 @@ pub struct TextRange {
   pub chunks: Vec<TextChunk>,
 }
 
+impl TextRange {
+  pub fn source_range(&self, start: usize, end: usize) -> (usize, usize) {
+    (
+      self.source_offset(start, false),
+      self.source_offset(end, true),
+    )
+  }
+
+  fn source_offset(&self, offset: usize, is_end: bool) -> usize {
+    // offsets within replaced chunks, like code spans, are clamped to the chunk in the source
+    let mut length = 0;
+    for chunk in &self.chunks {
+      let chunk_end = length + chunk.clean_length;
+      if offset < chunk_end || (is_end && offset == chunk_end) {
+        return chunk.start + (offset - length).min(chunk.end - chunk.start);
+      }
+      length = chunk_end;
+    }
+    self.chunks.last().map(|chunk| chunk.end).unwrap_or(0)
+  }
+}
+
 pub fn get_node_text(rope: &Rope, node: &Node) -> TextRange {
   let mut cursor = node.walk();
   let mut text = String::new();
@@ -74,6 +202,7 @@ pub fn get_node_text(rope: &Rope, node: &Node) -> TextRange {
       | 134 /* indented code block */
       | 201 /* html opening tag */
       | 202 /* html self-closing tag */
+      | 205 /* html comment */
       | 185 /* image */ => {
         recurse = false;
       },
@@ -0,0 +1,366 @@
+use crate::document::{diagnostic_suggestion, Fix};
+
+use lspower::lsp::{Diagnostic, DiagnosticSeverity, NumberOrString, Range};
+use serde_json::{json, Value};
+use std::{
+  io::{Result, Write},
+  path::PathBuf,
+  str::FromStr,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+  Human,
+  Json,
+  Sarif,
+  Checkstyle,
+  Junit,
+}
+
+impl Format {
+  pub const VARIANTS: &'static [&'static str] = &["human", "json", "sarif", "checkstyle", "junit"];
+}
+
+impl FromStr for Format {
+  type Err = String;
+
+  fn from_str(format: &str) -> std::result::Result<Self, Self::Err> {
+    match format.to_lowercase().as_ref() {
+      "human" => Ok(Format::Human),
+      "json" => Ok(Format::Json),
+      "sarif" => Ok(Format::Sarif),
+      "checkstyle" => Ok(Format::Checkstyle),
+      "junit" => Ok(Format::Junit),
+      other => Err(format!("unknown output format: {}", other)),
+    }
+  }
+}
+
+pub struct Issue {
+  pub diagnostic: Diagnostic,
+  pub fixes: Vec<Fix>,
+}
+
+impl Issue {
+  pub fn rule_id(&self) -> String {
+    diagnostic_suggestion(&self.diagnostic)
+      .map(|suggestion| suggestion.source)
+      .unwrap_or_else(|| "unknown".to_string())
+  }
+
+  pub fn rule_name(&self) -> Option<&str> {
+    match &self.diagnostic.code {
+      Some(NumberOrString::String(name)) => Some(name),
+      _other => None,
+    }
+  }
+
+  pub fn category(&self) -> &str {
+    self.diagnostic.source.as_deref().unwrap_or("other")
+  }
+
+  pub fn severity(&self) -> &'static str {
+    match self.diagnostic.severity {
+      Some(DiagnosticSeverity::Error) => "error",
+      Some(DiagnosticSeverity::Warning) => "warning",
+      Some(DiagnosticSeverity::Information) => "info",
+      Some(DiagnosticSeverity::Hint) => "hint",
+      None => "warning",
+    }
+  }
+
+  fn replacements(&self) -> Vec<&str> {
+    self
+      .fixes
+      .iter()
+      .map(|fix| fix.replacement.as_ref())
+      .collect()
+  }
+}
+
+pub struct FileReport {
+  pub path: PathBuf,
+  pub issues: Vec<Issue>,
+}
+
+impl FileReport {
+  fn uri(&self) -> String {
+    self.path.to_string_lossy().replace('\\', "/")
+  }
+}
+
+pub fn write_report(format: Format, reports: &[FileReport], out: &mut impl Write) -> Result<()> {
+  match format {
+    Format::Human => write_human(reports, out),
+    Format::Json => write_json(reports, out),
+    Format::Sarif => write_sarif(reports, out),
+    Format::Checkstyle => write_checkstyle(reports, out),
+    Format::Junit => write_junit(reports, out),
+  }
+}
+
+fn write_human(reports: &[FileReport], out: &mut impl Write) -> Result<()> {
+  for report in reports {
+    for issue in &report.issues {
+      writeln!(out, "{}", format_issue(report, issue))?;
+    }
+  }
+  Ok(())
+}
+
+fn format_issue(report: &FileReport, issue: &Issue) -> String {
+  let start = issue.diagnostic.range.start;
+  format!(
+    "{}:{}:{}: {} [{}] {}",
+    report.path.display(),
+    start.line + 1,
+    start.character + 1,
+    issue.severity(),
+    issue.rule_id(),
+    issue.diagnostic.message
+  )
+}
+
+fn write_json(reports: &[FileReport], out: &mut impl Write) -> Result<()> {
+  for report in reports {
+    for issue in &report.issues {
+      let fixes = issue
+        .fixes
+        .iter()
+        .map(|fix| {
+          json!({
+            "replacement": fix.replacement,
+            "edits": fix.edits,
+          })
+        })
+        .collect::<Vec<Value>>();
+
+      let line = json!({
+        "file": report.uri(),
+        "rule": issue.rule_id(),
+        "name": issue.rule_name(),
+        "category": issue.category(),
+        "severity": issue.severity(),
+        "message": issue.diagnostic.message,
+        "range": issue.diagnostic.range,
+        "fixes": fixes,
+      });
+      writeln!(out, "{}", line)?;
+    }
+  }
+  Ok(())
+}
+
+fn sarif_region(range: &Range) -> Value {
+  json!({
+    "startLine": range.start.line + 1,
+    "startColumn": range.start.character + 1,
+    "endLine": range.end.line + 1,
+    "endColumn": range.end.character + 1,
+  })
+}
+
+fn write_sarif(reports: &[FileReport], out: &mut impl Write) -> Result<()> {
+  let mut rules: Vec<Value> = Vec::new();
+  let mut rule_ids: Vec<String> = Vec::new();
+  let mut results: Vec<Value> = Vec::new();
+
+  for report in reports {
+    let uri = report.uri();
+    for issue in &report.issues {
+      let rule_id = issue.rule_id();
+      let rule_index = match rule_ids.iter().position(|id| *id == rule_id) {
+        Some(index) => index,
+        None => {
+          rules.push(json!({
+            "id": rule_id,
+            "name": issue.rule_name(),
+            "properties": { "category": issue.category() },
+          }));
+          rule_ids.push(rule_id.clone());
+          rule_ids.len() - 1
+        }
+      };
+
+      let level = match issue.diagnostic.severity {
+        Some(DiagnosticSeverity::Error) => "error",
+        Some(DiagnosticSeverity::Information) | Some(DiagnosticSeverity::Hint) => "note",
+        _other => "warning",
+      };
+
+      let fixes = issue
+        .fixes
+        .iter()
+        .map(|fix| {
+          let replacements = fix
+            .edits
+            .iter()
+            .map(|edit| {
+              json!({
+                "deletedRegion": sarif_region(&edit.range),
+                "insertedContent": { "text": edit.new_text },
+              })
+            })
+            .collect::<Vec<Value>>();
+          json!({
+            "description": { "text": format!("Replace with \"{}\"", fix.replacement) },
+            "artifactChanges": [{
+              "artifactLocation": { "uri": uri },
+              "replacements": replacements,
+            }],
+          })
+        })
+        .collect::<Vec<Value>>();
+
+      results.push(json!({
+        "ruleId": rule_id,
+        "ruleIndex": rule_index,
+        "level": level,
+        "message": { "text": issue.diagnostic.message },
+        "locations": [{
+          "physicalLocation": {
+            "artifactLocation": { "uri": uri },
+            "region": sarif_region(&issue.diagnostic.range),
+          },
+        }],
+        "fixes": fixes,
+        "properties": { "category": issue.category() },
+      }));
+    }
+  }
+
+  let sarif = json!({
+    "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+    "version": "2.1.0",
+    "runs": [{
+      "tool": {
+        "driver": {
+          "name": env!("CARGO_PKG_NAME"),
+          "version": env!("CARGO_PKG_VERSION"),
+          "informationUri": env!("CARGO_PKG_HOMEPAGE"),
+          "rules": rules,
+        },
+      },
+      "results": results,
+    }],
+  });
+
+  writeln!(out, "{}", serde_json::to_string_pretty(&sarif)?)
+}
+
+fn escape_xml(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&apos;"),
+      '\n' => escaped.push_str("&#10;"),
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+fn describe_issue(issue: &Issue) -> String {
+  let replacements = issue.replacements();
+  if replacements.is_empty() {
+    format!("{} ({})", issue.diagnostic.message, issue.category())
+  } else {
+    format!(
+      "{} ({}) Suggestions: {}",
+      issue.diagnostic.message,
+      issue.category(),
+      replacements.join(", ")
+    )
+  }
+}
+
+fn write_checkstyle(reports: &[FileReport], out: &mut impl Write) -> Result<()> {
+  writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+  writeln!(out, "<checkstyle version=\"4.3\">")?;
+  for report in reports {
+    writeln!(out, "  <file name=\"{}\">", escape_xml(&report.uri()))?;
+    for issue in &report.issues {
+      let start = issue.diagnostic.range.start;
+      let severity = match issue.severity() {
+        "hint" => "info",
+        severity => severity,
+      };
+      writeln!(
+        out,
+        "    <error line=\"{}\" column=\"{}\" severity=\"{}\" message=\"{}\" source=\"prosemd.{}.{}\"/>",
+        start.line + 1,
+        start.character + 1,
+        severity,
+        escape_xml(&describe_issue(issue)),
+        escape_xml(issue.category()),
+        escape_xml(&issue.rule_id())
+      )?;
+    }
+    writeln!(out, "  </file>")?;
+  }
+  writeln!(out, "</checkstyle>")
+}
+
+fn write_junit(reports: &[FileReport], out: &mut impl Write) -> Result<()> {
+  let tests = reports
+    .iter()
+    .map(|report| report.issues.len().max(1))
+    .sum::<usize>();
+  let failures = reports
+    .iter()
+    .map(|report| report.issues.len())
+    .sum::<usize>();
+
+  writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+  writeln!(
+    out,
+    "<testsuites name=\"prosemd\" tests=\"{}\" failures=\"{}\">",
+    tests, failures
+  )?;
+  for report in reports {
+    let uri = escape_xml(&report.uri());
+    writeln!(
+      out,
+      "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">",
+      uri,
+      report.issues.len().max(1),
+      report.issues.len()
+    )?;
+    if report.issues.is_empty() {
+      writeln!(
+        out,
+        "    <testcase name=\"{}\" classname=\"prosemd\"/>",
+        uri
+      )?;
+    }
+    for issue in &report.issues {
+      let start = issue.diagnostic.range.start;
+      writeln!(
+        out,
+        "    <testcase name=\"{}:{} {}\" classname=\"{}\">",
+        start.line + 1,
+        start.character + 1,
+        escape_xml(&issue.rule_id()),
+        uri
+      )?;
+      writeln!(
+        out,
+        "      <failure message=\"{}\" type=\"{}\">{}</failure>",
+        escape_xml(&issue.diagnostic.message),
+        issue.severity(),
+        escape_xml(&format!(
+          "{}\n{}",
+          format_issue(report, issue),
+          describe_issue(issue)
+        ))
+      )?;
+      writeln!(out, "    </testcase>")?;
+    }
+    writeln!(out, "  </testsuite>")?;
+  }
+  writeln!(out, "</testsuites>")
+}
@@ -0,0 +1,233 @@
+use crate::{
+  config::Config, directives::Directives, document::offset_to_position, validator::TextSuggestion,
+};
+
+use lspower::lsp::{Diagnostic, DiagnosticSeverity, NumberOrString, Range};
+use xi_rope::Rope;
+
+pub struct BuiltinRule {
+  pub id: &'static str,
+  pub description: &'static str,
+  pub category: &'static str,
+  pub severity: DiagnosticSeverity,
+}
+
+pub const UNSUPPORTED_LANGUAGE: BuiltinRule = BuiltinRule {
+  id: "UNSUPPORTED_LANGUAGE",
+  description: "Text is written in a language that can't be checked",
+  category: "language",
+  severity: DiagnosticSeverity::Information,
+};
+
+pub const UNDEFINED_REFERENCE: BuiltinRule = BuiltinRule {
+  id: "UNDEFINED_REFERENCE",
+  description: "A reference or footnote uses a label that isn't defined",
+  category: "links",
+  severity: DiagnosticSeverity::Warning,
+};
+
+pub const UNUSED_DEFINITION: BuiltinRule = BuiltinRule {
+  id: "UNUSED_DEFINITION",
+  description: "A link reference definition or footnote is never used",
+  category: "links",
+  severity: DiagnosticSeverity::Hint,
+};
+
+pub const MISSING_FILE: BuiltinRule = BuiltinRule {
+  id: "MISSING_FILE",
+  description: "A link points to a local file that doesn't exist",
+  category: "links",
+  severity: DiagnosticSeverity::Warning,
+};
+
+pub const MISSING_ANCHOR: BuiltinRule = BuiltinRule {
+  id: "MISSING_ANCHOR",
+  description: "A link points to a heading anchor that doesn't exist",
+  category: "links",
+  severity: DiagnosticSeverity::Warning,
+};
+
+pub const MISSING_IMAGE: BuiltinRule = BuiltinRule {
+  id: "MISSING_IMAGE",
+  description: "An image points to a local file that doesn't exist",
+  category: "links",
+  severity: DiagnosticSeverity::Warning,
+};
+
+pub const HEADING_INCREMENT: BuiltinRule = BuiltinRule {
+  id: "HEADING_INCREMENT",
+  description: "Heading levels should only increase by one level at a time",
+  category: "markdown",
+  severity: DiagnosticSeverity::Warning,
+};
+
+pub const SINGLE_H1: BuiltinRule = BuiltinRule {
+  id: "SINGLE_H1",
+  description: "A document should only have a single top-level heading",
+  category: "markdown",
+  severity: DiagnosticSeverity::Warning,
+};
+
+pub const LIST_MARKER_STYLE: BuiltinRule = BuiltinRule {
+  id: "LIST_MARKER_STYLE",
+  description: "Bullet lists should use the same marker throughout a document",
+  category: "markdown",
+  severity: DiagnosticSeverity::Information,
+};
+
+pub const EMPHASIS_STYLE: BuiltinRule = BuiltinRule {
+  id: "EMPHASIS_STYLE",
+  description: "Emphasis should use the same delimiters throughout a document",
+  category: "markdown",
+  severity: DiagnosticSeverity::Information,
+};
+
+pub const TRAILING_WHITESPACE: BuiltinRule = BuiltinRule {
+  id: "TRAILING_WHITESPACE",
+  description: "Lines shouldn't end with whitespace, unless it's a hard line break",
+  category: "markdown",
+  severity: DiagnosticSeverity::Information,
+};
+
+pub const HARD_TABS: BuiltinRule = BuiltinRule {
+  id: "HARD_TABS",
+  description: "Text should be indented with spaces instead of hard tabs",
+  category: "markdown",
+  severity: DiagnosticSeverity::Information,
+};
+
+pub const BLANK_LINES_AROUND_FENCES: BuiltinRule = BuiltinRule {
+  id: "BLANK_LINES_AROUND_FENCES",
+  description: "Fenced code blocks should be surrounded by blank lines",
+  category: "markdown",
+  severity: DiagnosticSeverity::Information,
+};
+
+pub const LONG_SENTENCE: BuiltinRule = BuiltinRule {
+  id: "LONG_SENTENCE",
+  description: "Sentences that are longer than the configured number of words are hard to follow",
+  category: "style",
+  severity: DiagnosticSeverity::Information,
+};
+
+pub const PASSIVE_VOICE: BuiltinRule = BuiltinRule {
+  id: "PASSIVE_VOICE",
+  description: "The active voice is usually clearer than the passive voice",
+  category: "style",
+  severity: DiagnosticSeverity::Hint,
+};
+
+pub const ADVERB: BuiltinRule = BuiltinRule {
+  id: "ADVERB",
+  description: "Adverbs can often be left out or replaced with a more precise verb",
+  category: "style",
+  severity: DiagnosticSeverity::Hint,
+};
+
+pub const WEASEL_WORD: BuiltinRule = BuiltinRule {
+  id: "WEASEL_WORD",
+  description: "Vague qualifiers, like \"very\" or \"simply\", weaken a statement",
+  category: "style",
+  severity: DiagnosticSeverity::Hint,
+};
+
+pub const REPEATED_SENTENCE_START: BuiltinRule = BuiltinRule {
+  id: "REPEATED_SENTENCE_START",
+  description: "Consecutive sentences shouldn't start with the same word",
+  category: "style",
+  severity: DiagnosticSeverity::Hint,
+};
+
+pub const BUILTIN_RULES: &[&BuiltinRule] = &[
+  &UNSUPPORTED_LANGUAGE,
+  &UNDEFINED_REFERENCE,
+  &UNUSED_DEFINITION,
+  &MISSING_FILE,
+  &MISSING_ANCHOR,
+  &MISSING_IMAGE,
+  &HEADING_INCREMENT,
+  &SINGLE_H1,
+  &LIST_MARKER_STYLE,
+  &EMPHASIS_STYLE,
+  &TRAILING_WHITESPACE,
+  &HARD_TABS,
+  &BLANK_LINES_AROUND_FENCES,
+  &LONG_SENTENCE,
+  &PASSIVE_VOICE,
+  &ADVERB,
+  &WEASEL_WORD,
+  &REPEATED_SENTENCE_START,
+];
+
+pub fn builtin_rule(id: &str) -> Option<&'static BuiltinRule> {
+  BUILTIN_RULES.iter().copied().find(|rule| rule.id == id)
+}
+
+impl BuiltinRule {
+  pub fn diagnostic(
+    &self,
+    rope: &Rope,
+    config: &Config,
+    directives: &Directives,
+    message: String,
+    start: usize,
+    end: usize,
+  ) -> Option<Diagnostic> {
+    let categories = [self.category];
+    let severity = config.severity(self.id, &categories, self.severity)?;
+    let range = Range::new(
+      offset_to_position(rope, start),
+      offset_to_position(rope, end),
+    );
+    if directives.is_suppressed(start, range.start.line as usize, self.id, &categories) {
+      return None;
+    }
+
+    // the suggestion carries the rule id, just like nlprule's suggestions, but has no alternatives
+    let suggestion = TextSuggestion {
+      source: self.id.to_string(),
+      message,
+      alternatives: Vec::new(),
+      start,
+      end,
+    };
+    Some(Diagnostic {
+      range,
+      severity: Some(severity),
+      code: Some(NumberOrString::String(self.id.to_string())),
+      source: Some(self.category.to_string()),
+      message: suggestion.message.clone(),
+      data: Some(serde_json::to_value(&suggestion).unwrap()),
+      ..Diagnostic::default()
+    })
+  }
+}
+
+// problems are found by the built-in checks, and only become diagnostics once they're configured
+pub struct Problem {
+  pub rule: &'static BuiltinRule,
+  pub message: String,
+  pub start: usize,
+  pub end: usize,
+}
+
+pub fn problem_diagnostics(
+  rope: &Rope,
+  config: &Config,
+  directives: &Directives,
+  problems: Vec<Problem>,
+) -> Vec<Diagnostic> {
+  problems
+    .into_iter()
+    .filter_map(|problem| {
+      problem.rule.diagnostic(
+        rope,
+        config,
+        directives,
+        problem.message,
+        problem.start,
+        problem.end,
+      )
+    })
+    .collect()
+}
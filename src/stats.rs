@@ -0,0 +1,240 @@
+use crate::{document::offset_to_position, headings::Heading, validator::TaggedWord};
+
+use lspower::lsp::{Position, Range};
+use serde::Serialize;
+use xi_rope::Rope;
+
+pub const DOCUMENT_STATS_METHOD: &str = "prosemd/documentStats";
+
+#[derive(Debug, Clone, Default)]
+pub struct Counts {
+  pub words: usize,
+  pub sentences: usize,
+  pub syllables: usize,
+}
+
+impl Counts {
+  pub fn from_sentences(sentences: &[Vec<TaggedWord>]) -> Self {
+    // sentences without any words, e.g. a lone punctuation mark, aren't counted
+    let mut counts = Counts::default();
+    for words in sentences.iter().filter(|words| !words.is_empty()) {
+      counts.sentences += 1;
+      counts.words += words.len();
+      counts.syllables += words
+        .iter()
+        .map(|word| syllables(&word.text))
+        .sum::<usize>();
+    }
+    counts
+  }
+
+  fn add(&mut self, other: &Counts) {
+    self.words += other.words;
+    self.sentences += other.sentences;
+    self.syllables += other.syllables;
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Metrics {
+  pub word_count: usize,
+  pub sentence_count: usize,
+  pub average_sentence_length: f64,
+  pub flesch_reading_ease: f64,
+  pub flesch_kincaid_grade: f64,
+}
+
+impl Metrics {
+  pub fn summary(&self) -> String {
+    if self.word_count == 0 {
+      return "No prose".to_string();
+    }
+    format!(
+      "{} words, {:.1} words per sentence, reading ease {:.1}, grade {:.1}",
+      self.word_count,
+      self.average_sentence_length,
+      self.flesch_reading_ease,
+      self.flesch_kincaid_grade
+    )
+  }
+}
+
+impl From<&Counts> for Metrics {
+  fn from(counts: &Counts) -> Self {
+    let (words, sentences, syllables) = (
+      counts.words as f64,
+      counts.sentences as f64,
+      counts.syllables as f64,
+    );
+    let (words_per_sentence, syllables_per_word) = if counts.words > 0 && counts.sentences > 0 {
+      (words / sentences, syllables / words)
+    } else {
+      (0.0, 0.0)
+    };
+    let (reading_ease, grade) = if counts.words > 0 {
+      (
+        206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word,
+        0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59,
+      )
+    } else {
+      (0.0, 0.0)
+    };
+    Self {
+      word_count: counts.words,
+      sentence_count: counts.sentences,
+      average_sentence_length: round(words_per_sentence),
+      flesch_reading_ease: round(reading_ease),
+      flesch_kincaid_grade: round(grade),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SectionStats {
+  pub heading: Option<String>,
+  pub range: Range,
+  #[serde(flatten)]
+  pub metrics: Metrics,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentStats {
+  #[serde(flatten)]
+  pub metrics: Metrics,
+  pub sections: Vec<SectionStats>,
+}
+
+pub fn document_stats(
+  rope: &Rope,
+  headings: &[Heading],
+  paragraphs: &[(usize, Counts)],
+) -> DocumentStats {
+  // each section spans from its heading up to the next heading, regardless of their levels
+  let mut sections: Vec<(Option<&Heading>, Counts)> = Vec::new();
+  sections.push((None, Counts::default()));
+  sections.extend(
+    headings
+      .iter()
+      .map(|heading| (Some(heading), Counts::default())),
+  );
+
+  let mut total = Counts::default();
+  for (offset, counts) in paragraphs {
+    total.add(counts);
+    let index = headings
+      .iter()
+      .take_while(|heading| heading.content_start <= *offset)
+      .count();
+    sections[index].1.add(counts);
+  }
+
+  let end = offset_to_position(rope, rope.len());
+  let starts = headings
+    .iter()
+    .map(|heading| heading.range.start)
+    .chain(std::iter::once(end))
+    .collect::<Vec<Position>>();
+  let sections = sections
+    .into_iter()
+    .enumerate()
+    // text before the first heading only forms a section when there is any
+    .filter(|(index, (_, counts))| *index > 0 || counts.words > 0)
+    .map(|(index, (heading, counts))| {
+      let start = match index {
+        0 => offset_to_position(rope, 0),
+        index => starts[index - 1],
+      };
+      SectionStats {
+        heading: heading.map(|heading| heading.text.clone()),
+        range: Range::new(start, starts[index]),
+        metrics: Metrics::from(&counts),
+      }
+    })
+    .collect();
+
+  DocumentStats {
+    metrics: Metrics::from(&total),
+    sections,
+  }
+}
+
+fn syllables(word: &str) -> usize {
+  // syllables are estimated by counting groups of vowels, which is what readability formulas expect
+  let word = word.to_lowercase();
+  let is_vowel = |c: char| matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+  let mut count = 0;
+  let mut previous_vowel = false;
+  for c in word.chars() {
+    let vowel = is_vowel(c);
+    if vowel && !previous_vowel {
+      count += 1;
+    }
+    previous_vowel = vowel;
+  }
+  // a silent "e" at the end of a word, like in "make", doesn't form a syllable
+  if count > 1 && word.ends_with('e') && !word.ends_with("le") && !word.ends_with("ee") {
+    count -= 1;
+  }
+  count.max(1)
+}
+
+fn round(value: f64) -> f64 {
+  (value * 10.0).round() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn estimates_syllables() {
+    let counts = [
+      "the",
+      "make",
+      "table",
+      "agree",
+      "syllable",
+      "readability",
+      "Prose",
+    ]
+    .iter()
+    .map(|word| syllables(word))
+    .collect::<Vec<usize>>();
+    assert_eq!(counts, [1, 1, 2, 2, 3, 5, 1]);
+  }
+
+  #[test]
+  fn computes_readability() {
+    // 20 words per sentence and 1.5 syllables per word
+    let metrics = Metrics::from(&Counts {
+      words: 100,
+      sentences: 5,
+      syllables: 150,
+    });
+    assert_eq!(metrics.average_sentence_length, 20.0);
+    assert_eq!(metrics.flesch_reading_ease, 59.6);
+    assert_eq!(metrics.flesch_kincaid_grade, 9.9);
+  }
+
+  #[test]
+  fn counts_sentences_with_words() {
+    let word = |text: &str| TaggedWord {
+      text: text.to_string(),
+      tags: Vec::new(),
+      start: 0,
+      end: 0,
+    };
+    let sentences = vec![vec![word("The"), word("cat"), word("sat")], Vec::new()];
+    let counts = Counts::from_sentences(&sentences);
+    assert_eq!(
+      (counts.words, counts.sentences, counts.syllables),
+      (3, 1, 3)
+    );
+    // "The cat sat." has 3 words and 3 syllables in a single sentence
+    assert_eq!(Metrics::from(&counts).flesch_reading_ease, 119.2);
+    assert_eq!(Metrics::from(&Counts::default()).summary(), "No prose");
+  }
+}
@@ -0,0 +1,298 @@
+use crate::{
+  config::{Config, Level},
+  directives::Directives,
+  node_util::TextRange,
+  rules::{
+    problem_diagnostics, BuiltinRule, Problem, ADVERB, LONG_SENTENCE, PASSIVE_VOICE,
+    REPEATED_SENTENCE_START, WEASEL_WORD,
+  },
+  validator::TaggedWord,
+};
+
+use lspower::lsp::Diagnostic;
+use xi_rope::Rope;
+
+const STYLE_RULES: &[&BuiltinRule] = &[
+  &LONG_SENTENCE,
+  &PASSIVE_VOICE,
+  &ADVERB,
+  &WEASEL_WORD,
+  &REPEATED_SENTENCE_START,
+];
+
+const WEASEL_WORDS: &[&str] = &[
+  "actually",
+  "arguably",
+  "basically",
+  "clearly",
+  "easily",
+  "extremely",
+  "fairly",
+  "just",
+  "largely",
+  "mostly",
+  "obviously",
+  "quite",
+  "rather",
+  "really",
+  "relatively",
+  "simply",
+  "somewhat",
+  "various",
+  "very",
+];
+
+pub fn is_enabled(config: &Config) -> bool {
+  // tagging sentences is only worth it when any of the style rules are turned on
+  STYLE_RULES
+    .iter()
+    .any(|rule| config.level(rule.id, &[rule.category]) != Level::Off)
+}
+
+pub fn check(
+  rope: &Rope,
+  text: &TextRange,
+  sentences: &[Vec<TaggedWord>],
+  config: &Config,
+  directives: &Directives,
+) -> Vec<Diagnostic> {
+  let mut problems = find_problems(sentences, config.max_sentence_length());
+  // words are found in the clean text, so their ranges are mapped back to the source
+  for problem in &mut problems {
+    let (start, end) = text.source_range(problem.start, problem.end);
+    problem.start = start;
+    problem.end = end;
+  }
+  problem_diagnostics(rope, config, directives, problems)
+}
+
+fn find_problems(sentences: &[Vec<TaggedWord>], max_length: usize) -> Vec<Problem> {
+  let mut problems: Vec<Problem> = Vec::new();
+  let mut previous_start: Option<String> = None;
+  for words in sentences.iter().filter(|words| !words.is_empty()) {
+    check_length(words, max_length, &mut problems);
+    check_passive_voice(words, &mut problems);
+    check_words(words, &mut problems);
+
+    let first = &words[0];
+    let start = first.text.to_lowercase();
+    if previous_start.as_ref() == Some(&start) {
+      problems.push(Problem {
+        rule: &REPEATED_SENTENCE_START,
+        message: format!(
+          "This sentence starts with \"{}\", like the one before it",
+          first.text
+        ),
+        start: first.start,
+        end: first.end,
+      });
+    }
+    previous_start = Some(start);
+  }
+  problems
+}
+
+fn check_length(words: &[TaggedWord], max_length: usize, problems: &mut Vec<Problem>) {
+  if words.len() > max_length {
+    problems.push(Problem {
+      rule: &LONG_SENTENCE,
+      message: format!(
+        "This sentence has {} words, which is more than {}",
+        words.len(),
+        max_length
+      ),
+      start: words[0].start,
+      end: words[words.len() - 1].end,
+    });
+  }
+}
+
+fn check_passive_voice(words: &[TaggedWord], problems: &mut Vec<Problem>) {
+  // a form of "to be" followed by a past participle, e.g. "is written" or "was quickly fixed"
+  for (index, word) in words.iter().enumerate() {
+    if !word.has_lemma("be") || !word.has_tag("VB") {
+      continue;
+    }
+    let participle = words[index + 1..]
+      .iter()
+      .find(|word| !word.has_tag("RB"))
+      .filter(|word| word.has_tag("VBN"));
+    if let Some(participle) = participle {
+      problems.push(Problem {
+        rule: &PASSIVE_VOICE,
+        message: format!(
+          "\"{} {}\" is in the passive voice",
+          word.text, participle.text
+        ),
+        start: word.start,
+        end: participle.end,
+      });
+    }
+  }
+}
+
+fn check_words(words: &[TaggedWord], problems: &mut Vec<Problem>) {
+  for word in words {
+    let lowercase = word.text.to_lowercase();
+    if WEASEL_WORDS.contains(&lowercase.as_ref()) {
+      problems.push(Problem {
+        rule: &WEASEL_WORD,
+        message: format!("\"{}\" is vague and weakens the statement", word.text),
+        start: word.start,
+        end: word.end,
+      });
+    } else if lowercase.ends_with("ly")
+      && lowercase != "only"
+      && word.has_tag("RB")
+      && !word.has_tag("JJ")
+    {
+      // only adverbs ending in "-ly" are reported, since others, like "not", are hard to avoid
+      problems.push(Problem {
+        rule: &ADVERB,
+        message: format!(
+          "\"{}\" is an adverb, which a more precise verb may replace",
+          word.text
+        ),
+        start: word.start,
+        end: word.end,
+      });
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::Settings;
+
+  // sentences are given as words with their lemma and part-of-speech tag, separated by spaces
+  fn check_text(
+    sentences: &[&[(&str, &str, &str)]],
+    max_length: usize,
+  ) -> Vec<(&'static str, String)> {
+    let mut text = String::new();
+    let sentences = sentences
+      .iter()
+      .map(|words| {
+        words
+          .iter()
+          .map(|(word, lemma, tag)| {
+            if !text.is_empty() {
+              text.push(' ');
+            }
+            let start = text.len();
+            text.push_str(word);
+            TaggedWord {
+              text: word.to_string(),
+              tags: vec![(lemma.to_string(), tag.to_string())],
+              start,
+              end: text.len(),
+            }
+          })
+          .collect()
+      })
+      .collect::<Vec<Vec<TaggedWord>>>();
+    find_problems(&sentences, max_length)
+      .into_iter()
+      .map(|problem| {
+        (
+          problem.rule.id,
+          text[problem.start..problem.end].to_string(),
+        )
+      })
+      .collect()
+  }
+
+  fn problem(id: &'static str, text: &str) -> (&'static str, String) {
+    (id, text.to_string())
+  }
+
+  const THE: (&str, &str, &str) = ("The", "the", "DT");
+  const BUG: (&str, &str, &str) = ("bug", "bug", "NN");
+  const TEAM: (&str, &str, &str) = ("team", "team", "NN");
+
+  #[test]
+  fn checks_sentence_length() {
+    let sentence: &[_] = &[THE, TEAM, ("fixed", "fix", "VBD"), ("it", "it", "PRP")];
+    assert_eq!(
+      check_text(&[sentence], 3),
+      [problem("LONG_SENTENCE", "The team fixed it")]
+    );
+    assert_eq!(check_text(&[sentence], 4), []);
+  }
+
+  #[test]
+  fn checks_passive_voice() {
+    let passive: &[_] = &[
+      THE,
+      BUG,
+      ("was", "be", "VBD"),
+      ("quickly", "quickly", "RB"),
+      ("fixed", "fix", "VBN"),
+    ];
+    assert_eq!(
+      check_text(&[passive], 30),
+      [
+        problem("PASSIVE_VOICE", "was quickly fixed"),
+        problem("ADVERB", "quickly"),
+      ]
+    );
+    let active: &[_] = &[
+      THE,
+      TEAM,
+      ("was", "be", "VBD"),
+      ("fixing", "fix", "VBG"),
+      ("it", "it", "PRP"),
+    ];
+    assert_eq!(check_text(&[active], 30), []);
+  }
+
+  #[test]
+  fn checks_adverbs_and_weasel_words() {
+    let sentence: &[_] = &[
+      ("Only", "only", "RB"),
+      ("friendly", "friendly", "JJ"),
+      ("bugs", "bug", "NNS"),
+      ("are", "be", "VBP"),
+      ("very", "very", "RB"),
+      ("easily", "easily", "RB"),
+      ("found", "find", "VBN"),
+    ];
+    assert_eq!(
+      check_text(&[sentence], 30),
+      [
+        problem("PASSIVE_VOICE", "are very easily found"),
+        problem("WEASEL_WORD", "very"),
+        problem("WEASEL_WORD", "easily"),
+      ]
+    );
+    let sentence: &[_] = &[
+      THE,
+      TEAM,
+      ("rarely", "rarely", "RB"),
+      ("sleeps", "sleep", "VBZ"),
+    ];
+    assert_eq!(check_text(&[sentence], 30), [problem("ADVERB", "rarely")]);
+  }
+
+  #[test]
+  fn checks_repeated_sentence_starts() {
+    let first: &[_] = &[THE, BUG];
+    let second: &[_] = &[("the", "the", "DT"), TEAM];
+    let third: &[_] = &[("A", "a", "DT"), TEAM];
+    assert_eq!(
+      check_text(&[first, second, third], 30),
+      [problem("REPEATED_SENTENCE_START", "the")]
+    );
+  }
+
+  #[test]
+  fn is_disabled_by_default() {
+    assert!(!is_enabled(&Config::default()));
+    let mut settings = Settings::default();
+    settings
+      .severity
+      .insert("PASSIVE_VOICE".to_string(), Level::Hint);
+    assert!(is_enabled(&Config::default().with_settings(&settings)));
+  }
+}
@@ -0,0 +1,174 @@
+use crate::{document::offset_to_position, node_util};
+
+use lspower::lsp::{DocumentSymbol, Range, SymbolKind};
+use tree_sitter::{Node, Tree};
+use xi_rope::Rope;
+
+struct Section {
+  level: usize,
+  start: usize,
+  symbol: DocumentSymbol,
+}
+
+pub fn document_symbols(rope: &Rope, tree: &Tree) -> Vec<DocumentSymbol> {
+  let mut symbols: Vec<DocumentSymbol> = Vec::new();
+  let mut sections: Vec<Section> = Vec::new();
+
+  let front_matter = node_util::find_front_matter(tree);
+  if let Some(node) = front_matter {
+    let range = Range::new(
+      offset_to_position(rope, 0),
+      offset_to_position(rope, node.end_byte()),
+    );
+    symbols.push(symbol(
+      "Front matter".to_string(),
+      None,
+      SymbolKind::Module,
+      range,
+      range,
+    ));
+  }
+
+  let nodes = node_util::find_nodes(
+    tree,
+    &[
+      129, /* setext heading */
+      132, /* atx heading */
+      136, /* fenced code block */
+      170, /* table */
+    ],
+  );
+  for node in nodes {
+    if Some(node) == front_matter {
+      continue;
+    }
+
+    if let Some(level) = node_util::heading_level(&node) {
+      // a heading closes all sections of the same or a deeper level
+      while matches!(sections.last(), Some(section) if section.level >= level) {
+        close_section(rope, &mut sections, &mut symbols, node.start_byte());
+      }
+      sections.push(Section {
+        level,
+        start: node.start_byte(),
+        symbol: heading_symbol(rope, &node),
+      });
+    } else {
+      let symbol = block_symbol(rope, &node);
+      match sections.last_mut() {
+        Some(section) => section
+          .symbol
+          .children
+          .get_or_insert_with(Vec::new)
+          .push(symbol),
+        None => symbols.push(symbol),
+      }
+    }
+  }
+
+  while !sections.is_empty() {
+    close_section(rope, &mut sections, &mut symbols, rope.len());
+  }
+
+  symbols
+}
+
+fn close_section(
+  rope: &Rope,
+  sections: &mut Vec<Section>,
+  symbols: &mut Vec<DocumentSymbol>,
+  end: usize,
+) {
+  if let Some(mut section) = sections.pop() {
+    // sections span up until the next heading, without the blank lines before it
+    let text = rope.slice_to_cow(section.start..end);
+    let end = offset_to_position(rope, section.start + text.trim_end().len());
+    section.symbol.range.end = end;
+
+    match sections.last_mut() {
+      Some(parent) => parent
+        .symbol
+        .children
+        .get_or_insert_with(Vec::new)
+        .push(section.symbol),
+      None => symbols.push(section.symbol),
+    }
+  }
+}
+
+fn heading_symbol(rope: &Rope, node: &Node) -> DocumentSymbol {
+  let range = node_range(rope, node);
+  let selection_range = node_util::heading_content(node)
+    .map(|content| node_range(rope, &content))
+    .unwrap_or(range);
+  let name = node_util::heading_content(node)
+    .map(|content| {
+      rope
+        .slice_to_cow(content.start_byte()..content.end_byte())
+        .trim()
+        .to_string()
+    })
+    .filter(|name| !name.is_empty())
+    .unwrap_or_else(|| "(empty heading)".to_string());
+  symbol(name, None, SymbolKind::String, range, selection_range)
+}
+
+fn block_symbol(rope: &Rope, node: &Node) -> DocumentSymbol {
+  let range = node_range(rope, node);
+  let mut cursor = node.walk();
+  let (name, detail, kind) = match node.kind_id() {
+    136 /* fenced code block */ => {
+      let info = node
+        .children(&mut cursor)
+        .find(|child| child.kind_id() == 231 /* info string */)
+        .map(|info| {
+          rope
+            .slice_to_cow(info.start_byte()..info.end_byte())
+            .trim()
+            .to_string()
+        });
+      let name = match &info {
+        Some(info) => format!("Code block ({})", info),
+        None => "Code block".to_string(),
+      };
+      (name, info, SymbolKind::Object)
+    }
+    _other => {
+      let columns = node
+        .children(&mut cursor)
+        .find(|child| child.kind_id() == 172 /* table header row */)
+        .map(|row| row.named_child_count())
+        .unwrap_or_default();
+      let detail = format!("{} columns", columns);
+      ("Table".to_string(), Some(detail), SymbolKind::Array)
+    }
+  };
+  symbol(name, detail, kind, range, range)
+}
+
+fn node_range(rope: &Rope, node: &Node) -> Range {
+  Range::new(
+    offset_to_position(rope, node.start_byte()),
+    offset_to_position(rope, node.end_byte()),
+  )
+}
+
+#[allow(deprecated)]
+fn symbol(
+  name: String,
+  detail: Option<String>,
+  kind: SymbolKind,
+  range: Range,
+  selection_range: Range,
+) -> DocumentSymbol {
+  DocumentSymbol {
+    name,
+    detail,
+    kind,
+    tags: None,
+    deprecated: None,
+    range,
+    selection_range,
+    children: None,
+  }
+}
@@ -0,0 +1,227 @@
+use crate::format::Edit;
+
+use tree_sitter::Node;
+use unicode_width::UnicodeWidthStr;
+use xi_rope::Rope;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Alignment {
+  None,
+  Left,
+  Center,
+  Right,
+}
+
+struct Cell {
+  start: usize,
+  end: usize,
+  content_start: usize,
+  content_end: usize,
+  width: usize,
+}
+
+struct Row<'a> {
+  node: Node<'a>,
+  cells: Vec<Cell>,
+}
+
+pub fn format_table(rope: &Rope, table: &Node) -> Vec<Edit> {
+  let mut rows: Vec<Row> = Vec::new();
+  let mut delimiter_row: Option<Node> = None;
+  let mut alignments: Vec<Alignment> = Vec::new();
+
+  let mut cursor = table.walk();
+  for row in table.children(&mut cursor) {
+    match row.kind_id() {
+      172 /* table header row */ | 176 /* table data row */ => rows.push(Row {
+        cells: parse_cells(rope, &row),
+        node: row,
+      }),
+      174 /* table delimiter row */ => {
+        let mut cursor = row.walk();
+        alignments = row
+          .children(&mut cursor)
+          .filter(|child| child.kind_id() == 107 /* table column alignment */)
+          .map(|child| parse_alignment(&node_text(rope, &child)))
+          .collect();
+        delimiter_row = Some(row);
+      }
+      _other => (),
+    }
+  }
+
+  let delimiter_row = match delimiter_row {
+    Some(delimiter_row) if !alignments.is_empty() => delimiter_row,
+    _other => return Vec::new(),
+  };
+
+  // cells past the header's columns aren't part of the table, and are left as they are
+  let columns = alignments.len();
+  let mut widths = vec![3; columns];
+  for row in &rows {
+    for (cell, width) in row.cells.iter().zip(widths.iter_mut()) {
+      *width = (*width).max(cell.width);
+    }
+  }
+
+  let mut edits: Vec<Edit> = Vec::new();
+  for row in &rows {
+    let source = node_text(rope, &row.node);
+    let has_leading_pipe = source.trim_start().starts_with('|');
+    let has_trailing_pipe = source.trim_end().ends_with('|');
+    for (index, cell) in row.cells.iter().take(columns).enumerate() {
+      let padding = widths[index] - cell.width;
+      let (left, right) = match alignments[index] {
+        Alignment::Right => (padding, 0),
+        Alignment::Center => (padding / 2, padding - padding / 2),
+        Alignment::None | Alignment::Left => (0, padding),
+      };
+      let leading_pipe = if index == 0 && !has_leading_pipe {
+        "|"
+      } else {
+        ""
+      };
+      let trailing_pipe = if index + 1 == row.cells.len() && !has_trailing_pipe {
+        "|"
+      } else {
+        ""
+      };
+      let leading = format!("{} {}", leading_pipe, " ".repeat(left));
+      let trailing = format!("{} {}", " ".repeat(right), trailing_pipe);
+
+      if cell.content_start == cell.content_end {
+        edits.extend(Edit::minimal(
+          rope,
+          cell.start,
+          cell.end,
+          format!("{}{}", leading, trailing),
+        ));
+      } else {
+        edits.extend(Edit::minimal(rope, cell.start, cell.content_start, leading));
+        edits.extend(Edit::minimal(rope, cell.content_end, cell.end, trailing));
+      }
+    }
+
+    // short rows are completed with empty cells, which are inserted after the row's last pipe
+    let last_cell = match row.cells.last() {
+      Some(last_cell) if row.cells.len() < columns => last_cell,
+      _other => continue,
+    };
+    let end = if has_trailing_pipe {
+      row.node.start_byte() + source.trim_end().len()
+    } else {
+      last_cell.end
+    };
+    let missing = widths[row.cells.len()..]
+      .iter()
+      .map(|width| format!(" {} |", " ".repeat(*width)))
+      .collect::<String>();
+    edits.push(Edit::new(end, end, missing));
+  }
+
+  let delimiters = alignments
+    .iter()
+    .zip(widths.iter())
+    .map(|(alignment, width)| delimiter(*alignment, *width))
+    .collect::<Vec<String>>();
+  let text = format!("| {} |", delimiters.join(" | "));
+  let source = node_text(rope, &delimiter_row);
+  let start = delimiter_row.start_byte() + (source.len() - source.trim_start().len());
+  edits.extend(Edit::minimal(rope, start, delimiter_row.end_byte(), text));
+  edits
+}
+
+fn parse_cells(rope: &Rope, row: &Node) -> Vec<Cell> {
+  let mut cursor = row.walk();
+  row
+    .children(&mut cursor)
+    .filter(|child| child.kind_id() == 235 /* table cell */)
+    .map(|cell| {
+      let text = node_text(rope, &cell);
+      let content = text.trim();
+      let content_start = cell.start_byte() + (text.len() - text.trim_start().len());
+      Cell {
+        start: cell.start_byte(),
+        end: cell.end_byte(),
+        content_start,
+        content_end: content_start + content.len(),
+        width: UnicodeWidthStr::width(content),
+      }
+    })
+    .collect()
+}
+
+fn parse_alignment(text: &str) -> Alignment {
+  let text = text.trim();
+  match (text.starts_with(':'), text.ends_with(':')) {
+    (true, true) => Alignment::Center,
+    (true, false) => Alignment::Left,
+    (false, true) => Alignment::Right,
+    (false, false) => Alignment::None,
+  }
+}
+
+fn delimiter(alignment: Alignment, width: usize) -> String {
+  match alignment {
+    Alignment::None => "-".repeat(width),
+    Alignment::Left => format!(":{}", "-".repeat(width - 1)),
+    Alignment::Right => format!("{}:", "-".repeat(width - 1)),
+    Alignment::Center => format!(":{}:", "-".repeat(width - 2)),
+  }
+}
+
+fn node_text(rope: &Rope, node: &Node) -> String {
+  rope
+    .slice_to_cow(node.start_byte()..node.end_byte())
+    .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{format::tests::apply_edits, node_util, parser::parser};
+
+  fn format_text(text: &str) -> String {
+    let tree = parser().parse(text, None).unwrap();
+    let rope = Rope::from(text);
+    let mut edits = node_util::find_nodes(&tree, &[170 /* table */])
+      .iter()
+      .flat_map(|table| format_table(&rope, table))
+      .collect::<Vec<Edit>>();
+    edits.sort_by_key(|edit| (edit.start, edit.end));
+    apply_edits(text, edits)
+  }
+
+  #[test]
+  fn aligns_columns() {
+    let text = "| a | bb | c |\n|:-|-:|:-:|\n| ccc | d | eeeee |\n";
+    assert_eq!(
+      format_text(text),
+      "| a   |  bb |   c   |\n| :-- | --: | :---: |\n| ccc |   d | eeeee |\n"
+    );
+  }
+
+  #[test]
+  fn keeps_aligned_tables() {
+    let text = "| a   | b   |\n| --- | --- |\n| c   | d   |\n";
+    assert_eq!(format_text(text), text);
+  }
+
+  #[test]
+  fn adds_missing_pipes() {
+    let text = "a | b\n--|--\nc | d\n";
+    assert_eq!(
+      format_text(text),
+      "| a   | b   |\n| --- | --- |\n| c   | d   |\n"
+    );
+  }
+
+  #[test]
+  fn completes_short_rows() {
+    let text = "| a | b | c |\n|---|---|---|\n| 1 |\n| 2 | 3\n";
+    assert_eq!(
+      format_text(text),
+      "| a   | b   | c   |\n| --- | --- | --- |\n| 1   |     |     |\n| 2   | 3   |     |\n"
+    );
+  }
+}
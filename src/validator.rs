@@ -1,24 +1,94 @@
-use crate::node_util::{TextChunk, TextRange};
+use crate::{
+  dictionary::Dictionary,
+  language,
+  node_util::{TextChunk, TextRange},
+};
 
 use flate2::read::GzDecoder;
 use lru::LruCache;
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, Mutex};
+use std::{
+  collections::{BTreeMap, HashMap},
+  fs::File,
+  io::{BufReader, Read},
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex},
+};
+use xz2::read::XzDecoder;
 
 use nlprule::{
   rule::Rule, rules_filename, tokenizer_filename, types::Suggestion, Rules, Tokenizer,
 };
 
-static TOKENIZER_GZ: &[u8] = include_bytes!(concat!(
+static EN_TOKENIZER_GZ: &[u8] = include_bytes!(concat!(
   env!("OUT_DIR"),
   "/",
   tokenizer_filename!("en"),
   ".gz"
 ));
 
-static RULES_GZ: &[u8] =
+static EN_RULES_GZ: &[u8] =
   include_bytes!(concat!(env!("OUT_DIR"), "/", rules_filename!("en"), ".gz"));
 
+#[cfg(feature = "de")]
+static DE_TOKENIZER_GZ: &[u8] = include_bytes!(concat!(
+  env!("OUT_DIR"),
+  "/",
+  tokenizer_filename!("de"),
+  ".gz"
+));
+
+#[cfg(feature = "de")]
+static DE_RULES_GZ: &[u8] =
+  include_bytes!(concat!(env!("OUT_DIR"), "/", rules_filename!("de"), ".gz"));
+
+#[cfg(feature = "es")]
+static ES_TOKENIZER_GZ: &[u8] = include_bytes!(concat!(
+  env!("OUT_DIR"),
+  "/",
+  tokenizer_filename!("es"),
+  ".gz"
+));
+
+#[cfg(feature = "es")]
+static ES_RULES_GZ: &[u8] =
+  include_bytes!(concat!(env!("OUT_DIR"), "/", rules_filename!("es"), ".gz"));
+
+const NLPRULE_VERSION: &str = "0.4.6";
+
+fn embedded_binaries(language: &str) -> (Option<&'static [u8]>, Option<&'static [u8]>) {
+  // languages other than english are only embedded when their cargo feature is enabled
+  match language {
+    "en" => (Some(EN_TOKENIZER_GZ), Some(EN_RULES_GZ)),
+    #[cfg(feature = "de")]
+    "de" => (Some(DE_TOKENIZER_GZ), Some(DE_RULES_GZ)),
+    #[cfg(feature = "es")]
+    "es" => (Some(ES_TOKENIZER_GZ), Some(ES_RULES_GZ)),
+    _other => (None, None),
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BinaryPaths {
+  pub rules: Option<PathBuf>,
+  pub tokenizer: Option<PathBuf>,
+}
+
+pub type Binaries = BTreeMap<&'static str, BinaryPaths>;
+
+pub fn binaries_from_files(rules: &[PathBuf], tokenizers: &[PathBuf]) -> Binaries {
+  let mut binaries = Binaries::new();
+  for path in rules {
+    let language = language::from_binary_path(path);
+    binaries.entry(language).or_default().rules = Some(path.clone());
+  }
+  for path in tokenizers {
+    let language = language::from_binary_path(path);
+    binaries.entry(language).or_default().tokenizer = Some(path.clone());
+  }
+  binaries
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Replacement {
   pub replacement: String,
@@ -26,15 +96,39 @@ pub struct Replacement {
   pub end: usize,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct Alternative {
+  pub text: String,
+  pub replacements: Vec<Replacement>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct TextSuggestion {
   pub source: String,
   pub message: String,
-  pub replacements: Vec<Replacement>,
+  pub alternatives: Vec<Alternative>,
+  pub start: usize,
+  pub end: usize,
+}
+
+pub struct TaggedWord {
+  pub text: String,
+  // each possible reading of a word is a pair of its lemma and part-of-speech tag
+  pub tags: Vec<(String, String)>,
   pub start: usize,
   pub end: usize,
 }
 
+impl TaggedWord {
+  pub fn has_tag(&self, prefix: &str) -> bool {
+    self.tags.iter().any(|(_, pos)| pos.starts_with(prefix))
+  }
+
+  pub fn has_lemma(&self, lemma: &str) -> bool {
+    self.tags.iter().any(|(word_lemma, _)| word_lemma == lemma)
+  }
+}
+
 pub struct Validator {
   cache: Arc<Mutex<LruCache<String, Vec<Suggestion>>>>,
   tokenizer: Tokenizer,
@@ -42,40 +136,43 @@ pub struct Validator {
 }
 
 impl Validator {
-  pub fn new() -> Self {
-    log::debug!("initializing validator...");
+  pub fn new(language: &str, paths: &BinaryPaths) -> Option<Self> {
+    log::debug!("initializing validator for \"{}\"...", language);
+    let (tokenizer_gz, rules_gz) = embedded_binaries(language);
     let cache = Arc::new(Mutex::new(LruCache::new(1000)));
-    let tokenizer = Tokenizer::from_reader(&mut GzDecoder::new(TOKENIZER_GZ)).unwrap();
-    let rules = Rules::from_reader(&mut GzDecoder::new(RULES_GZ))
-      .unwrap()
-      .into_iter()
-      .map(|mut rule| {
-        rule.set_on(match rule.id().to_lowercase().as_ref() {
-          // informally, "todo" is just as well accepted as "to-do"
-          "to_do_hyphen.3" => false,
-          "to_do_hyphen.2" => false,
-          _other => match rule.category_id().to_lowercase().as_ref() {
-            // Wikipedia's style guide contains a few rules that are too opinionated
-            "wikipedia" => false,
-            "typography" => false,
-            _other => true,
-          },
-        });
-        rule
-      })
-      .collect::<Rules>();
-    Self {
+    let tokenizer = load_binary(
+      "tokenizer",
+      language,
+      paths.tokenizer.as_deref(),
+      tokenizer_gz,
+      Tokenizer::from_reader,
+    )?;
+    let rules = load_binary(
+      "rules",
+      language,
+      paths.rules.as_deref(),
+      rules_gz,
+      Rules::from_reader,
+    )?
+    .into_iter()
+    .map(|mut rule| {
+      // rules are filtered per document by the `Config` instead
+      rule.set_on(true);
+      rule
+    })
+    .collect::<Rules>();
+    Some(Self {
       cache,
       tokenizer,
       rules,
-    }
+    })
   }
 
   pub fn get_rule(&self, id: &str) -> Option<&Rule> {
     self.rules.rule(id)
   }
 
-  pub fn suggest(&self, text: &TextRange) -> Vec<TextSuggestion> {
+  pub fn suggest(&self, text: &TextRange, dictionary: &Dictionary) -> Vec<TextSuggestion> {
     let mut cache = self.cache.lock().unwrap();
     let suggestions = match cache.get(&text.clean_text) {
       Some(suggestions) => suggestions.clone(),
@@ -88,36 +185,206 @@ impl Validator {
 
     suggestions
       .into_iter()
+      .filter(|suggestion| !self.is_known_word(text, suggestion, dictionary))
       .filter_map(|suggestion| compute_edit(&text, suggestion))
       .collect()
   }
+
+  pub fn sentences(&self, text: &str) -> Vec<Vec<TaggedWord>> {
+    self
+      .tokenizer
+      .pipe(text)
+      .into_iter()
+      .map(|tokens| {
+        tokens
+          .into_iter()
+          // only tokens with letters or digits are words, unlike punctuation and whitespace
+          .filter(|token| {
+            token
+              .word
+              .text
+              .as_ref()
+              .chars()
+              .any(|c| c.is_alphanumeric())
+          })
+          .map(|token| {
+            // token spans are relative to their sentence, which is a slice of the text
+            let offset = token.sentence.as_ptr() as usize - text.as_ptr() as usize;
+            TaggedWord {
+              text: token.word.text.as_ref().to_string(),
+              tags: token
+                .word
+                .tags
+                .iter()
+                .map(|data| {
+                  (
+                    data.lemma.as_ref().to_string(),
+                    data.pos.as_ref().to_string(),
+                  )
+                })
+                .collect(),
+              start: offset + token.byte_span.0,
+              end: offset + token.byte_span.1,
+            }
+          })
+          .collect()
+      })
+      .collect()
+  }
+
+  fn is_known_word(
+    &self,
+    text: &TextRange,
+    suggestion: &Suggestion,
+    dictionary: &Dictionary,
+  ) -> bool {
+    let is_misspelling = self
+      .get_rule(&suggestion.source)
+      .and_then(|rule| rule.category_type())
+      == Some("misspelling");
+    if is_misspelling {
+      // suggestions are given in character indices rather than byte offsets
+      let word = text
+        .clean_text
+        .chars()
+        .skip(suggestion.start)
+        .take(suggestion.end - suggestion.start)
+        .collect::<String>();
+      dictionary.contains(&word)
+    } else {
+      false
+    }
+  }
+}
+
+type ValidatorKey = (&'static str, BinaryPaths);
+
+#[derive(Default)]
+pub struct Validators {
+  validators: Mutex<HashMap<ValidatorKey, Option<Arc<Validator>>>>,
+}
+
+impl Validators {
+  pub fn get(&self, language: &'static str, paths: BinaryPaths) -> Option<Arc<Validator>> {
+    // validators are only loaded once a document of their language comes up, and only tried once
+    let mut validators = self.validators.lock().unwrap();
+    validators
+      .entry((language, paths))
+      .or_insert_with_key(|(language, paths)| Validator::new(language, paths).map(Arc::new))
+      .clone()
+  }
+}
+
+fn open_binary(path: &Path) -> std::io::Result<Box<dyn Read>> {
+  let file = BufReader::new(File::open(path)?);
+  let name = path.to_string_lossy();
+  Ok(if name.ends_with(".gz") {
+    Box::new(GzDecoder::new(file))
+  } else if name.ends_with(".xz") {
+    Box::new(XzDecoder::new(file))
+  } else {
+    Box::new(file)
+  })
+}
+
+fn load_binary<T>(
+  kind: &str,
+  language: &str,
+  path: Option<&Path>,
+  embedded_gz: Option<&'static [u8]>,
+  from_reader: fn(Box<dyn Read>) -> Result<T, nlprule::Error>,
+) -> Option<T> {
+  if let Some(path) = path {
+    match open_binary(path)
+      .map_err(nlprule::Error::from)
+      .and_then(from_reader)
+    {
+      Ok(binary) => return Some(binary),
+      Err(nlprule::Error::Deserialization(err)) => log::error!(
+        "{} {} isn't compatible with nlprule v{}, using the built-in {} instead: {}",
+        kind,
+        path.display(),
+        NLPRULE_VERSION,
+        kind,
+        err
+      ),
+      Err(err) => log::error!(
+        "failed to load {} {}, using the built-in {} instead: {}",
+        kind,
+        path.display(),
+        kind,
+        err
+      ),
+    }
+  }
+
+  match embedded_gz {
+    Some(embedded_gz) => Some(from_reader(Box::new(GzDecoder::new(embedded_gz))).unwrap()),
+    None => {
+      log::error!(
+        "no {} for \"{}\" is built in, so it has to be loaded with --{} or a config file",
+        kind,
+        language,
+        kind
+      );
+      None
+    }
+  }
 }
 
 fn compute_edit(text: &TextRange, suggestion: Suggestion) -> Option<TextSuggestion> {
-  let mut chunks = slice_textchunks_for_suggestion(&text, &suggestion);
+  // nlprule counts characters, while the clean text and its chunks are sliced by bytes
+  let clean_start = byte_offset(&text.clean_text, suggestion.start);
+  let clean_end = byte_offset(&text.clean_text, suggestion.end);
+  let chunks = slice_textchunks_for_suggestion(text, clean_start, clean_end);
   if !chunks.is_empty() {
     let start = chunks.first().unwrap().start;
     let end = chunks.last().unwrap().end;
-    let left = &text.clean_text[suggestion.start..suggestion.end];
-    let right = &suggestion.replacements.first().unwrap().clone();
-    let mut diff = diff::chars(left, right);
-
-    let mut replacements: Vec<Replacement> = Vec::new();
-    while let Some(chunk) = chunks.pop() {
-      let replacement = take_diff_last(&mut diff, chunk.clean_length);
-      if !replacement.is_empty() {
-        replacements.push(Replacement {
-          replacement,
-          start: chunk.start,
-          end: chunk.end,
-        });
-      }
-    }
+    let left = &text.clean_text[clean_start..clean_end];
+
+    // diffs are made of characters, so they're taken from each chunk by its number of characters
+    let mut offset = clean_start;
+    let chunk_chars = chunks
+      .iter()
+      .map(|chunk| {
+        let chars = text.clean_text[offset..offset + chunk.clean_length]
+          .chars()
+          .count();
+        offset += chunk.clean_length;
+        chars
+      })
+      .collect::<Vec<usize>>();
+
+    // alternatives are kept in the order they're ranked in
+    let alternatives = suggestion
+      .replacements
+      .iter()
+      .map(|right| {
+        let mut diff = diff::chars(left, right);
+        let mut replacements: Vec<Replacement> = Vec::new();
+        for (index, (chunk, chars)) in chunks.iter().zip(chunk_chars.iter()).enumerate().rev() {
+          // the first chunk also takes what's inserted before the suggestion's text
+          let chars = if index == 0 { usize::MAX } else { *chars };
+          let replacement = take_diff_last(&mut diff, chars);
+          if !replacement.is_empty() {
+            replacements.push(Replacement {
+              replacement,
+              start: chunk.start,
+              end: chunk.end,
+            });
+          }
+        }
+        Alternative {
+          text: right.clone(),
+          replacements,
+        }
+      })
+      .collect();
 
     Some(TextSuggestion {
       source: suggestion.source,
       message: suggestion.message,
-      replacements,
+      alternatives,
       start,
       end,
     })
@@ -126,14 +393,26 @@ fn compute_edit(text: &TextRange, suggestion: Suggestion) -> Option<TextSuggesti
   }
 }
 
-fn slice_textchunks_for_suggestion(text: &TextRange, suggestion: &Suggestion) -> Vec<TextChunk> {
+fn byte_offset(text: &str, char_offset: usize) -> usize {
+  text
+    .char_indices()
+    .nth(char_offset)
+    .map(|(offset, _)| offset)
+    .unwrap_or_else(|| text.len())
+}
+
+fn slice_textchunks_for_suggestion(
+  text: &TextRange,
+  clean_start: usize,
+  clean_end: usize,
+) -> Vec<TextChunk> {
   let mut chunks: Vec<TextChunk> = Vec::new();
   let mut length = 0;
   let mut index = -1;
 
   for chunk in &text.chunks {
     length += chunk.clean_length;
-    if length >= suggestion.start {
+    if length >= clean_start {
       index += 1;
 
       let mut clean_length = chunk.clean_length;
@@ -141,12 +420,12 @@ fn slice_textchunks_for_suggestion(text: &TextRange, suggestion: &Suggestion) ->
       let mut end = chunk.end;
 
       if index == 0 {
-        clean_length = length - suggestion.start;
+        clean_length = length - clean_start;
         start = chunk.end - clean_length;
       }
 
-      if length >= suggestion.end {
-        let slice = length - suggestion.end;
+      if length >= clean_end {
+        let slice = length - clean_end;
         clean_length -= slice;
         end = chunk.end - slice;
         chunks.push(TextChunk {
@@ -171,7 +450,7 @@ fn slice_textchunks_for_suggestion(text: &TextRange, suggestion: &Suggestion) ->
 fn take_diff_last(diff: &mut Vec<diff::Result<char>>, take_last: usize) -> String {
   let mut chars: Vec<char> = Vec::new();
   let mut taken = 0;
-  while taken <= take_last {
+  while taken < take_last {
     match diff.pop() {
       Some(diff::Result::Right(c)) => {
         // added
@@ -192,3 +471,49 @@ fn take_diff_last(diff: &mut Vec<diff::Result<char>>, take_last: usize) -> Strin
 
   chars.into_iter().rev().collect()
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{fix::apply_suggestions, node_util, parser::parser};
+  use xi_rope::Rope;
+
+  fn suggest(text: &str, word: &str, replacement: &str) -> String {
+    let tree = parser().parse(text, None).unwrap();
+    let rope = Rope::from(text);
+    let node = node_util::find_text_nodes(&tree)[0];
+    let range = node_util::get_node_text(&rope, &node);
+    // like nlprule's, the suggestion's range is given in characters
+    let offset = range.clean_text.find(word).unwrap();
+    let start = range.clean_text[..offset].chars().count();
+    let suggestion = Suggestion {
+      source: "TEST".to_string(),
+      message: String::new(),
+      start,
+      end: start + word.chars().count(),
+      replacements: vec![replacement.to_string()],
+    };
+    let edit = compute_edit(&range, suggestion).unwrap();
+    apply_suggestions(text, &[edit]).0
+  }
+
+  #[test]
+  fn replaces_text_after_non_ascii_words() {
+    assert_eq!(
+      suggest("Schöne Grüße aus Köln mit Fehlr.\n", "Fehlr", "Fehler"),
+      "Schöne Grüße aus Köln mit Fehler.\n"
+    );
+    assert_eq!(
+      suggest("La *señora* está aqui.\n", "aqui", "aquí"),
+      "La *señora* está aquí.\n"
+    );
+  }
+
+  #[test]
+  fn replaces_non_ascii_text_across_chunks() {
+    assert_eq!(
+      suggest("Ein *größe* Fehler.\n", "größe Fehler", "großer Fehler"),
+      "Ein *großer* Fehler.\n"
+    );
+  }
+}
@@ -0,0 +1,201 @@
+use crate::{document::Document, headings::Heading};
+
+use percent_encoding::percent_decode_str;
+use std::{
+  collections::{HashMap, HashSet},
+  fs,
+  io::Result,
+  path::{Component, Path, PathBuf},
+};
+use url::Url;
+
+// directories with dependencies or build output, which aren't worth indexing
+const IGNORED_DIRS: &[&str] = &["node_modules", "target", "vendor"];
+
+#[derive(Default)]
+pub struct Workspace {
+  headings: HashMap<PathBuf, Vec<Heading>>,
+  images: HashSet<PathBuf>,
+}
+
+impl Workspace {
+  pub fn from_dir(dir: &Path) -> Self {
+    let mut files: Vec<PathBuf> = Vec::new();
+    let is_linkable = |file: &Path| is_markdown_file(file) || is_image_file(file);
+    if let Err(err) = find_files(dir, &is_linkable, &mut files) {
+      log::warn!("failed to index {}: {}", dir.display(), err);
+    }
+
+    let mut workspace = Workspace::default();
+    for path in files {
+      workspace.update(&path);
+    }
+    workspace
+  }
+
+  pub fn update(&mut self, path: &Path) {
+    // images are only indexed by their paths, so that they can be completed
+    if is_image_file(path) {
+      if path.is_file() {
+        self.images.insert(path.to_path_buf());
+      } else {
+        self.images.remove(path);
+      }
+      return;
+    }
+
+    match fs::read_to_string(path) {
+      Ok(text) => {
+        let headings = Document::new(text).headings();
+        self.headings.insert(path.to_path_buf(), headings);
+      }
+      Err(_) => self.remove(path),
+    }
+  }
+
+  pub fn merge(&mut self, other: Workspace) {
+    // entries that are already present are at least as recent as the ones that were read before
+    for (path, headings) in other.headings {
+      self.headings.entry(path).or_insert(headings);
+    }
+    self.images.extend(other.images);
+  }
+
+  pub fn insert(&mut self, path: &Path, headings: Vec<Heading>) {
+    self.headings.insert(path.to_path_buf(), headings);
+  }
+
+  pub fn remove(&mut self, path: &Path) {
+    self.headings.remove(path);
+    self.images.remove(path);
+  }
+
+  pub fn paths(&self) -> Vec<PathBuf> {
+    self.headings.keys().cloned().collect()
+  }
+
+  pub fn files(&self) -> Vec<PathBuf> {
+    let mut files = self
+      .headings
+      .keys()
+      .chain(self.images.iter())
+      .cloned()
+      .collect::<Vec<PathBuf>>();
+    files.sort();
+    files
+  }
+
+  pub fn headings(&mut self, path: &Path) -> Option<&Vec<Heading>> {
+    // files that weren't indexed yet, e.g. outside of the workspace root, are read on demand
+    if !self.headings.contains_key(path) && is_markdown_file(path) && path.is_file() {
+      self.update(path);
+    }
+    self.headings.get(path)
+  }
+}
+
+pub struct LinkTarget {
+  pub uri: Url,
+  pub fragment: Option<String>,
+}
+
+pub fn resolve_link(base: &Url, root: Option<&Path>, destination: &str) -> Option<LinkTarget> {
+  let destination = destination.trim();
+  let mut uri = if let Some(fragment) = destination.strip_prefix('#') {
+    let mut uri = base.clone();
+    uri.set_fragment(Some(fragment));
+    uri
+  } else if let Ok(uri) = Url::parse(destination) {
+    uri
+  } else if let (Some(root), Some(path)) = (root, destination.strip_prefix('/')) {
+    // absolute paths are relative to the workspace root, like they are on GitHub
+    let root = Url::from_directory_path(root).ok()?;
+    root.join(path).ok()?
+  } else {
+    base.join(destination).ok()?
+  };
+
+  if uri.scheme() != "file" {
+    return None;
+  }
+
+  let fragment = uri
+    .fragment()
+    .filter(|fragment| !fragment.is_empty())
+    .map(|fragment| {
+      percent_decode_str(fragment)
+        .decode_utf8_lossy()
+        .into_owned()
+    });
+  uri.set_fragment(None);
+  uri.set_query(None);
+  Some(LinkTarget { uri, fragment })
+}
+
+pub fn walk_dir(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+  find_files(dir, &is_markdown_file, files)
+}
+
+pub fn find_files(
+  dir: &Path,
+  matches: &dyn Fn(&Path) -> bool,
+  files: &mut Vec<PathBuf>,
+) -> Result<()> {
+  let mut entries = fs::read_dir(dir)?
+    .filter_map(|entry| entry.ok())
+    .map(|entry| entry.path())
+    .collect::<Vec<PathBuf>>();
+  entries.sort();
+
+  for path in entries {
+    let file_name = path.file_name().and_then(|name| name.to_str());
+    if matches!(file_name, Some(name) if name.starts_with('.')) {
+      continue;
+    } else if path.is_dir() {
+      if !matches!(file_name, Some(name) if IGNORED_DIRS.contains(&name)) {
+        find_files(&path, matches, files)?;
+      }
+    } else if matches(&path) {
+      files.push(path);
+    }
+  }
+
+  Ok(())
+}
+
+pub fn is_markdown_file(path: &Path) -> bool {
+  match path.extension().and_then(|ext| ext.to_str()) {
+    Some(ext) => matches!(ext.to_lowercase().as_ref(), "md" | "markdown"),
+    None => false,
+  }
+}
+
+pub fn is_image_file(path: &Path) -> bool {
+  match path.extension().and_then(|ext| ext.to_str()) {
+    Some(ext) => matches!(
+      ext.to_lowercase().as_ref(),
+      "png" | "jpg" | "jpeg" | "gif" | "svg" | "webp" | "avif" | "bmp" | "ico"
+    ),
+    None => false,
+  }
+}
+
+pub fn relative_path(dir: &Path, path: &Path) -> Option<String> {
+  let dir = dir.components().collect::<Vec<Component>>();
+  let path = path.components().collect::<Vec<Component>>();
+  let common = dir
+    .iter()
+    .zip(path.iter())
+    .take_while(|(a, b)| a == b)
+    .count();
+  // paths on different drives or roots can't be linked relatively
+  if common == 0 {
+    return None;
+  }
+
+  let parents = dir[common..].iter().map(|_| "..".to_string());
+  let children = path[common..]
+    .iter()
+    .map(|component| component.as_os_str().to_string_lossy().replace(' ', "%20"));
+  Some(parents.chain(children).collect::<Vec<String>>().join("/"))
+}